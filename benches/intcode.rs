@@ -0,0 +1,181 @@
+//! Benchmarks of the IntCode VM on the day 2 and day 5 puzzle inputs, with and
+//! without the decode cache and compiled to closures. Puzzle inputs are not part of
+//! the repository, put them into `input/2019/` to run the benchmarks. The decode
+//! and countdown benchmarks need no input and compare against the string based
+//! decoder the VM used before.
+
+use advent_of_code_2019::intcode::compiler::CompiledProgram;
+use advent_of_code_2019::intcode::{Instruction, IntCodeVM, OpCode, ParamMode};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use std::collections::VecDeque;
+use std::path::Path;
+
+fn load(day: u32) -> Option<IntCodeVM> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("input/2019/day{}.txt", day));
+
+    match IntCodeVM::from_file(&path) {
+        Ok(vm) => Some(vm),
+        Err(e) => {
+            eprintln!("skipping day {} benchmarks, {}: {}", day, path.display(), e);
+            None
+        }
+    }
+}
+
+// counts the input down to zero, executing two instructions per iteration
+const COUNTDOWN: &str = "3,11,1001,11,-1,11,1005,11,2,99,0,0";
+
+// the decoder before instructions were decoded arithmetically
+fn decode_baseline(i: u32) -> (OpCode, Vec<ParamMode>) {
+    let digits = format!("{:0>2}", i)
+        .chars()
+        .map(|c| c.to_digit(10).unwrap())
+        .rev()
+        .collect::<Vec<u32>>();
+
+    let code = OpCode::from(digits[0] + (10 * digits[1]));
+    let parameter_modes = digits[2..].iter().map(|&m| m.into()).collect();
+
+    (code, parameter_modes)
+}
+
+fn bench_decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+
+    group.bench_function("arithmetic", |b| {
+        b.iter(|| {
+            for i in &[1, 2, 1002, 1101, 21107, 204, 99] {
+                black_box(Instruction::from_int(black_box(*i)));
+            }
+        })
+    });
+
+    group.bench_function("baseline", |b| {
+        b.iter(|| {
+            for i in &[1, 2, 1002, 1101, 21107, 204, 99] {
+                black_box(decode_baseline(black_box(*i)));
+            }
+        })
+    });
+
+    group.finish();
+}
+
+fn bench_countdown(c: &mut Criterion) {
+    let vm: IntCodeVM = COUNTDOWN.parse().unwrap();
+    let run = |vm: &mut IntCodeVM| vm.run(&mut VecDeque::from(vec![10_000]), &mut ()).unwrap();
+
+    // the countdown leaves memory as it found it, so after one run the VM only
+    // has to be rewound to start again with every instruction decoded
+    let mut warm = vm.clone();
+    run(&mut warm);
+    warm.jmp(0);
+    warm.set_halted(false);
+    assert_eq!(vm, warm);
+
+    let mut group = c.benchmark_group("countdown");
+
+    group.bench_function("uncached", |b| {
+        b.iter(|| {
+            let mut vm = vm.clone();
+            vm.set_decode_cache(false);
+            run(&mut vm)
+        })
+    });
+
+    group.bench_function("cold", |b| b.iter(|| run(&mut vm.clone())));
+    group.bench_function("warm", |b| b.iter(|| run(&mut warm.clone())));
+
+    group.finish();
+}
+
+fn bench_day2(c: &mut Criterion) {
+    let vm = match load(2) {
+        Some(vm) => vm,
+        None => return,
+    };
+
+    let mut group = c.benchmark_group("day2");
+
+    for &cached in &[true, false] {
+        let name = if cached { "cached" } else { "uncached" };
+
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let mut vm = vm.clone();
+                vm.set_decode_cache(cached);
+                vm.set_int(1, 12).unwrap();
+                vm.set_int(2, 2).unwrap();
+                vm.run(&mut (), &mut ()).unwrap();
+
+                vm.read_int(0).unwrap()
+            })
+        });
+    }
+
+    // compiled once, noun and verb are patched like on every run of part 2
+    let compiled = CompiledProgram::new(&vm);
+
+    group.bench_function("compiled", |b| {
+        b.iter(|| {
+            let mut vm = vm.clone();
+            vm.set_int(1, 12).unwrap();
+            vm.set_int(2, 2).unwrap();
+            compiled.run(&mut vm, &mut (), &mut ()).unwrap();
+
+            vm.read_int(0).unwrap()
+        })
+    });
+
+    group.finish();
+}
+
+fn bench_day5(c: &mut Criterion) {
+    let vm = match load(5) {
+        Some(vm) => vm,
+        None => return,
+    };
+
+    let mut group = c.benchmark_group("day5");
+
+    for &cached in &[true, false] {
+        let name = if cached { "cached" } else { "uncached" };
+
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let mut vm = vm.clone();
+                vm.set_decode_cache(cached);
+                let mut output = Vec::new();
+                vm.run(&mut VecDeque::from(vec![5]), &mut output).unwrap();
+
+                output
+            })
+        });
+    }
+
+    let compiled = CompiledProgram::new(&vm);
+
+    group.bench_function("compiled", |b| {
+        b.iter(|| {
+            let mut vm = vm.clone();
+            let mut output = Vec::new();
+            compiled
+                .run(&mut vm, &mut VecDeque::from(vec![5]), &mut output)
+                .unwrap();
+
+            output
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_decode,
+    bench_countdown,
+    bench_day2,
+    bench_day5
+);
+criterion_main!(benches);
@@ -0,0 +1,13 @@
+//! Parses arbitrary bytes as program and runs it in every configuration of the VM.
+
+#![no_main]
+
+use advent_of_code_2019::intcode::fuzz::check;
+use advent_of_code_2019::intcode::IntCodeVM;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(vm) = IntCodeVM::<i64>::from_bytes(data) {
+        check(vm.memory().as_slice().unwrap(), &[0, 1, 8]);
+    }
+});
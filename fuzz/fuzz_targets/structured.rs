@@ -0,0 +1,13 @@
+//! Turns arbitrary bytes into a program of valid instructions and runs it in every
+//! configuration of the VM. The first byte is used as input.
+
+#![no_main]
+
+use advent_of_code_2019::intcode::fuzz::{check, structured_program};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Some((input, program)) = data.split_first() {
+        check(&structured_program(program), &[i64::from(*input as i8)]);
+    }
+});
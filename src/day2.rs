@@ -1,97 +1,92 @@
+use crate::intcode::compiler::CompiledProgram;
+use crate::intcode::symbolic::{solve, SymbolicVM};
+use crate::intcode::IntCodeVM;
 use aoc_runner_derive::{aoc, aoc_generator};
 
-type IntCResult<T> = Result<T, String>;
-
-struct IntComputer {
-    code: Vec<u32>,
-    pos: usize,
+#[aoc_generator(day2)]
+fn input_generator(input: &str) -> IntCodeVM {
+    input.parse().unwrap()
 }
 
-impl IntComputer {
-    fn new(code: Vec<u32>) -> Self {
-        Self { code, pos: 0 }
-    }
-
-    fn get_integer_at(&self, index: usize) -> IntCResult<u32> {
-        match self.code.get(index) {
-            Some(c) => Ok(*c),
-            None => Err(format!("no integer found at position {}", index)),
-        }
-    }
+/// Run the program with the given noun and verb and return the value left at position 0.
+/// Noun and verb are parameters, so the program only has to be compiled once.
+fn run_with(program: &CompiledProgram, vm: &IntCodeVM, noun: i64, verb: i64) -> i64 {
+    let mut vm = vm.clone();
+    vm.set_int(1, noun).unwrap();
+    vm.set_int(2, verb).unwrap();
+    program.run(&mut vm, &mut (), &mut ()).unwrap();
 
-    fn set_integer_at(&mut self, index: usize, value: u32) -> IntCResult<()> {
-        match self.code.get_mut(index) {
-            Some(val) => {
-                *val = value;
-
-                Ok(())
-            }
-            None => Err(format!("no integer found at position {}", index)),
-        }
-    }
+    vm.read_int(0).unwrap()
+}
 
-    fn run(&mut self) -> IntCResult<()> {
-        while let Some(c) = self.code.get(self.pos) {
-            let lhs = self.get_integer_at(self.get_integer_at(self.pos + 1)? as usize);
-            let rhs = self.get_integer_at(self.get_integer_at(self.pos + 2)? as usize);
-            let result_pos = self.get_integer_at(self.pos + 3);
-
-            match *c {
-                // Add
-                1 => self.set_integer_at(result_pos? as usize, lhs? + rhs?)?,
-                // Mul
-                2 => self.set_integer_at(result_pos? as usize, lhs? * rhs?)?,
-                // Exit
-                99 => return Ok(()),
-                // invalid
-                _ => {
-                    return Err(format!(
-                        "Not a valid instruction code: {}, pos: {}",
-                        c, self.pos
-                    ))
-                }
-            }
+#[aoc(day2, part1)]
+pub fn solve_part_1(vm: &IntCodeVM) -> i64 {
+    run_with(&CompiledProgram::new(vm), vm, 12, 2)
+}
 
-            self.pos += 4;
-        }
+#[aoc(day2, part2)]
+fn solve_part_2(vm: &IntCodeVM) -> i64 {
+    let (noun, verb) = find_noun_and_verb(vm, 19_690_720).unwrap();
 
-        Ok(())
-    }
+    100 * noun + verb
 }
 
-#[aoc_generator(day2)]
-fn input_generator(input: &str) -> Vec<u32> {
-    input
-        .split(',')
-        .map(|s| s.parse::<u32>().unwrap())
-        .collect()
+/// Find the noun and verb between 0 and 99 that make the program leave `target` at
+/// position 0. The program is executed symbolically and the result solved for noun
+/// and verb. Solutions are confirmed by a concrete run. If the program can't be
+/// executed symbolically, or no solution is found or holds up, the search falls back
+/// to trying every noun and verb.
+fn find_noun_and_verb(vm: &IntCodeVM, target: i64) -> Option<(i64, i64)> {
+    solve_noun_and_verb(vm, target).or_else(|| search_noun_and_verb(vm, target))
 }
 
-#[aoc(day2, part1)]
-pub fn solve_part_1(code: &[u32]) -> u32 {
-    let mut computer = IntComputer::new(code.to_vec());
-    computer.set_integer_at(1, 12).unwrap();
-    computer.set_integer_at(2, 2).unwrap();
-    computer.run().unwrap();
+fn solve_noun_and_verb(vm: &IntCodeVM, target: i64) -> Option<(i64, i64)> {
+    let mut symbolic = SymbolicVM::new(vm);
+    symbolic.set_symbol(1, "noun").ok()?;
+    symbolic.set_symbol(2, "verb").ok()?;
+    symbolic.run().ok()?;
 
-    computer.get_integer_at(0).unwrap()
+    let result = symbolic.read(0)?;
+    let solution = solve(&result, &target, &[("noun", 0..=99), ("verb", 0..=99)])?;
+    let (noun, verb) = (solution["noun"], solution["verb"]);
+
+    Some((noun, verb)).filter(|_| run_with(&CompiledProgram::new(vm), vm, noun, verb) == target)
 }
 
-#[aoc(day2, part2)]
-fn solve_part_2(code: &[u32]) -> u32 {
-    for x in 0..100 {
-        for y in 0..100 {
-            let mut computer = IntComputer::new(code.to_vec());
-            computer.set_integer_at(1, x).unwrap();
-            computer.set_integer_at(2, y).unwrap();
-            computer.run().unwrap();
-            let res = computer.get_integer_at(0).unwrap();
-
-            if res == 19_690_720 {
-                return 100 * x + y;
+fn search_noun_and_verb(vm: &IntCodeVM, target: i64) -> Option<(i64, i64)> {
+    let program = CompiledProgram::new(vm);
+
+    for noun in 0..100 {
+        for verb in 0..100 {
+            if run_with(&program, vm, noun, verb) == target {
+                return Some((noun, verb));
             }
         }
     }
 
-    unreachable!()
+    None
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::day2::*;
+
+    #[test]
+    fn test_run_with() {
+        let vm = input_generator("1,0,0,0,99\n");
+        let program = CompiledProgram::new(&vm);
+
+        assert_eq!(2, run_with(&program, &vm, 0, 0));
+        assert_eq!(100, run_with(&program, &vm, 4, 0));
+    }
+
+    #[test]
+    fn test_find_noun_and_verb() {
+        // ((noun + verb) * 7) + 1
+        let vm = input_generator("1,0,0,3,1,1,2,3,2,3,17,3,1,3,18,0,99,7,1\n");
+
+        assert_eq!(Some((1, 99)), find_noun_and_verb(&vm, 701));
+        assert_eq!(search_noun_and_verb(&vm, 701), find_noun_and_verb(&vm, 701));
+        assert_eq!(None, find_noun_and_verb(&vm, 700));
+    }
 }
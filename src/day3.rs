@@ -1,7 +1,6 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 
 use std::collections::HashSet;
-use std::error::Error;
 use std::iter::FromIterator;
 use std::str::FromStr;
 
@@ -37,9 +36,7 @@ impl FromStr for Direction {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let (dir, len) = s.split_at(1);
-        let len = len
-            .parse::<i32>()
-            .map_err(|e| e.description().to_string())?;
+        let len = len.parse::<i32>().map_err(|e| e.to_string())?;
 
         match dir {
             "R" => Ok(Direction::Right(len)),
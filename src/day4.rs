@@ -88,6 +88,7 @@ fn solve_part_2(passwords: &[Password]) -> usize {
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 pub mod tests {
     use crate::day4::*;
 
@@ -0,0 +1,275 @@
+//! Talking to programs that read and write ASCII text.
+//!
+//! Such programs emit character codes and read input one character code at a time,
+//! each line terminated by a newline. Values outside the ASCII range usually carry a
+//! result, e.g. the amount of dust collected on day 17, and are kept as numbers.
+
+use crate::intcode::{IntCodeError, IntCodeVM, Status, Word};
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io::{self, BufRead, Write};
+
+/// A piece of decoded output.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Chunk<W> {
+    /// Consecutive ASCII characters.
+    Text(String),
+    /// A value outside the ASCII range.
+    Value(W),
+}
+
+impl<W: Word> Display for Chunk<W> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Chunk::Text(text) => write!(f, "{}", text),
+            Chunk::Value(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// Text to be sent to a program contained a character outside the ASCII range.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct NonAsciiError {
+    pub character: char,
+    /// The position of the character in the text in characters.
+    pub position: usize,
+}
+
+impl Display for NonAsciiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "non-ASCII character {:?} at position {}",
+            self.character, self.position
+        )
+    }
+}
+
+impl Error for NonAsciiError {}
+
+/// The character codes of the given text.
+pub fn encode<W: Word>(text: &str) -> Result<Vec<W>, NonAsciiError> {
+    text.chars()
+        .enumerate()
+        .map(|(position, character)| {
+            if character.is_ascii() {
+                Ok(W::from_i64(character as i64).expect("ASCII codes fit into every word"))
+            } else {
+                Err(NonAsciiError {
+                    character,
+                    position,
+                })
+            }
+        })
+        .collect()
+}
+
+/// Turn output values into text, keeping values outside the ASCII range as numbers.
+pub fn decode<W: Word>(values: &[W]) -> Vec<Chunk<W>> {
+    let mut chunks = Vec::new();
+
+    for value in values {
+        let character = value
+            .to_i64()
+            .filter(|code| (0..=127).contains(code))
+            .map(|code| code as u8 as char);
+
+        match (character, chunks.last_mut()) {
+            (Some(character), Some(Chunk::Text(text))) => text.push(character),
+            (Some(character), _) => chunks.push(Chunk::Text(character.to_string())),
+            (None, _) => chunks.push(Chunk::Value(value.clone())),
+        }
+    }
+
+    chunks
+}
+
+/// Wraps a VM to exchange lines of text with it.
+#[derive(Debug, Clone)]
+pub struct AsciiVM<W: Word = i64> {
+    vm: IntCodeVM<W>,
+}
+
+impl<W: Word> AsciiVM<W> {
+    pub fn new(vm: IntCodeVM<W>) -> Self {
+        AsciiVM { vm }
+    }
+
+    pub fn vm(&self) -> &IntCodeVM<W> {
+        &self.vm
+    }
+
+    pub fn into_inner(self) -> IntCodeVM<W> {
+        self.vm
+    }
+
+    /// Queue a line of input, terminated by a newline unless it already is.
+    pub fn push_line(&mut self, line: &str) -> Result<(), NonAsciiError> {
+        let mut codes = encode(line)?;
+
+        if !line.ends_with('\n') {
+            codes.extend(encode("\n")?);
+        }
+
+        for code in codes {
+            self.vm.push_input(code);
+        }
+
+        Ok(())
+    }
+
+    /// Run until the program needs more input than was queued or halts and return
+    /// its output.
+    pub fn run(&mut self) -> Result<Vec<Chunk<W>>, IntCodeError> {
+        let mut output = Vec::new();
+
+        loop {
+            match self.vm.resume()? {
+                Status::Output(value) => output.push(value),
+                Status::NeedsInput | Status::Halted => return Ok(decode(&output)),
+            }
+        }
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.vm.is_halted()
+    }
+
+    /// Run the program, writing its output to `out` and feeding it lines read from
+    /// `lines` whenever it needs input, until it halts, fails or the input ends.
+    /// Values outside the ASCII range are written on a line of their own.
+    pub fn interact<R: BufRead, O: Write>(&mut self, lines: R, mut out: O) -> io::Result<()> {
+        let mut lines = lines.lines();
+
+        loop {
+            match self.run() {
+                Ok(chunks) => {
+                    for chunk in chunks {
+                        match chunk {
+                            Chunk::Text(text) => write!(out, "{}", text)?,
+                            Chunk::Value(value) => writeln!(out, "{}", value)?,
+                        }
+                    }
+                }
+                Err(e) => return writeln!(out, "error: {}", e),
+            }
+
+            out.flush()?;
+
+            if self.is_halted() {
+                return Ok(());
+            }
+
+            loop {
+                let line = match lines.next() {
+                    Some(line) => line?,
+                    None => return Ok(()),
+                };
+
+                match self.push_line(&line) {
+                    Ok(()) => break,
+                    Err(e) => writeln!(out, "error: {}", e)?,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::intcode::ascii::*;
+    use crate::intcode::asm::assemble_to_string;
+
+    // greets, echoes a line and reports its length as a value outside the ASCII range
+    const ECHO: &str = "
+                OUT #72
+                OUT #105
+                OUT #10
+        loop:   IN -> [c]
+                OUT [c]
+                ADD [count], #1 -> [count]
+                EQ [c], #10 -> [flag]
+                JF [flag], #loop
+                ADD [count], #999 -> [count]
+                OUT [count]
+                HLT
+        c:      .data 0
+        count:  .data 0
+        flag:   .data 0
+    ";
+
+    fn echo() -> AsciiVM {
+        AsciiVM::new(assemble_to_string(ECHO).unwrap().parse().unwrap())
+    }
+
+    #[test]
+    fn test_ascii_encode_and_decode() {
+        assert_eq!(Ok(vec![104, 105, 10]), encode::<i64>("hi\n"));
+        assert_eq!(
+            Err(NonAsciiError {
+                character: 'é',
+                position: 2
+            }),
+            encode::<i64>("caé")
+        );
+        assert_eq!(
+            "non-ASCII character 'é' at position 2",
+            encode::<i64>("caé").unwrap_err().to_string()
+        );
+
+        assert_eq!(
+            vec![
+                Chunk::Text("ab".to_string()),
+                Chunk::Value(128),
+                Chunk::Value(-1),
+                Chunk::Text("\n".to_string()),
+            ],
+            decode(&[97, 98, 128, -1, 10])
+        );
+        assert!(decode::<i64>(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_ascii_vm() {
+        let mut vm = echo();
+        assert_eq!(Ok(vec![Chunk::Text("Hi\n".to_string())]), vm.run());
+        assert!(!vm.is_halted());
+
+        vm.push_line("hello").unwrap();
+        let output = vm.run().unwrap();
+        assert_eq!(
+            vec![Chunk::Text("hello\n".to_string()), Chunk::Value(1005)],
+            output
+        );
+        assert_eq!(
+            "hello\n1005",
+            output.iter().map(ToString::to_string).collect::<String>()
+        );
+        assert!(vm.is_halted());
+    }
+
+    #[test]
+    fn test_ascii_interact() {
+        let mut out = Vec::new();
+        echo().interact("é\nok\n".as_bytes(), &mut out).unwrap();
+        assert_eq!(
+            "Hi\nerror: non-ASCII character 'é' at position 0\nok\n1002\n",
+            String::from_utf8(out).unwrap()
+        );
+
+        // the program waits for more input when the input ends
+        let mut out = Vec::new();
+        echo().interact("".as_bytes(), &mut out).unwrap();
+        assert_eq!("Hi\n", String::from_utf8(out).unwrap());
+
+        let mut out = Vec::new();
+        AsciiVM::<i64>::new("3,0,98".parse().unwrap())
+            .interact("x".as_bytes(), &mut out)
+            .unwrap();
+        assert_eq!(
+            "error: invalid opcode 98 in instruction 98 (???) at position 2\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+}
@@ -0,0 +1,515 @@
+//! A small assembly language for IntCode programs.
+//!
+//! ```text
+//! ; read a number and print it twice
+//! start:  IN -> [value]
+//!         OUT [value]
+//!         OUT [value]
+//!         HLT
+//! value:  .data 0
+//! ```
+//!
+//! Operands are written as `#imm` (immediate), `[pos]` (positional) or `rel+N`
+//! (relative). Immediate and positional operands accept a number, a label or a
+//! label with an offset such as `[value+1]`. The destination of instructions that
+//! write a result may be separated by `->` or by a comma. Everything after `;` is
+//! a comment. Lines may start with a numeric address like `0012:`, as printed by
+//! the disassembler, which must match the address the line is assembled to.
+
+use crate::intcode::{OpCode, ParamMode};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AssemblyError {
+    UnknownMnemonic {
+        line: usize,
+        mnemonic: String,
+    },
+    InvalidOperand {
+        line: usize,
+        operand: String,
+    },
+    WrongOperandCount {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    ImmediateWrite {
+        line: usize,
+    },
+    UnknownLabel {
+        line: usize,
+        label: String,
+    },
+    DuplicateLabel {
+        line: usize,
+        label: String,
+    },
+    AddressMismatch {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// An address annotation too large to be an address.
+    InvalidAddress {
+        line: usize,
+        address: String,
+    },
+    /// A label plus its offset doesn't fit into a cell.
+    OffsetOverflow {
+        line: usize,
+        label: String,
+        offset: i64,
+    },
+}
+
+impl Display for AssemblyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssemblyError::UnknownMnemonic { line, mnemonic } => {
+                write!(f, "line {}: unknown mnemonic {}", line, mnemonic)
+            }
+            AssemblyError::InvalidOperand { line, operand } => {
+                write!(f, "line {}: invalid operand {}", line, operand)
+            }
+            AssemblyError::WrongOperandCount {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {}: expected {} operands, found {}",
+                line, expected, found
+            ),
+            AssemblyError::ImmediateWrite { line } => {
+                write!(f, "line {}: cannot write to an immediate operand", line)
+            }
+            AssemblyError::UnknownLabel { line, label } => {
+                write!(f, "line {}: unknown label {}", line, label)
+            }
+            AssemblyError::DuplicateLabel { line, label } => {
+                write!(f, "line {}: label {} is already defined", line, label)
+            }
+            AssemblyError::AddressMismatch {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {}: line is annotated with address {} but assembles to {}",
+                line, expected, found
+            ),
+            AssemblyError::InvalidAddress { line, address } => {
+                write!(f, "line {}: invalid address {}", line, address)
+            }
+            AssemblyError::OffsetOverflow {
+                line,
+                label,
+                offset,
+            } => write!(f, "line {}: {}{:+} is out of range", line, label, offset),
+        }
+    }
+}
+
+impl Error for AssemblyError {}
+
+/// A number, optionally relative to a label.
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Expr {
+    label: Option<String>,
+    offset: i64,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Operand {
+    Positional(Expr),
+    Immediate(Expr),
+    Relative(i64),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Statement {
+    Instruction(OpCode, Vec<Operand>),
+    Data(Vec<Expr>),
+}
+
+impl Statement {
+    fn len(&self) -> usize {
+        match self {
+            Statement::Instruction(_, operands) => 1 + operands.len(),
+            Statement::Data(values) => values.len(),
+        }
+    }
+}
+
+/// Assemble the given source into a memory image.
+pub fn assemble(source: &str) -> Result<Vec<i64>, AssemblyError> {
+    let mut labels = HashMap::new();
+    let mut statements = Vec::new();
+    let mut address = 0;
+
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        let mut text = text.split(';').next().unwrap().trim();
+
+        while let Some(colon) = text.find(':') {
+            let label = text[..colon].trim();
+
+            if label.chars().all(|c| c.is_ascii_digit()) && !label.is_empty() {
+                let expected = label.parse().map_err(|_| AssemblyError::InvalidAddress {
+                    line,
+                    address: label.to_string(),
+                })?;
+
+                if expected != address {
+                    return Err(AssemblyError::AddressMismatch {
+                        line,
+                        expected,
+                        found: address,
+                    });
+                }
+            } else if is_label(label) {
+                if labels.insert(label.to_string(), address).is_some() {
+                    return Err(AssemblyError::DuplicateLabel {
+                        line,
+                        label: label.to_string(),
+                    });
+                }
+            } else {
+                break;
+            }
+
+            text = text[colon + 1..].trim();
+        }
+
+        if text.is_empty() {
+            continue;
+        }
+
+        let statement = parse_statement(line, text)?;
+        address += statement.len();
+        statements.push((line, statement));
+    }
+
+    let mut program = Vec::with_capacity(address);
+
+    for (line, statement) in statements {
+        match statement {
+            Statement::Instruction(code, operands) => {
+                let mut instruction = i64::from(u32::from(code));
+                let mut factor = 100;
+
+                for operand in &operands {
+                    let mode = match operand {
+                        Operand::Positional(_) => ParamMode::Positional,
+                        Operand::Immediate(_) => ParamMode::Immediate,
+                        Operand::Relative(_) => ParamMode::Relative,
+                    };
+
+                    instruction += factor * i64::from(u32::from(mode));
+                    factor *= 10;
+                }
+
+                program.push(instruction);
+
+                for operand in operands {
+                    program.push(match operand {
+                        Operand::Positional(expr) | Operand::Immediate(expr) => {
+                            resolve(line, &expr, &labels)?
+                        }
+                        Operand::Relative(offset) => offset,
+                    });
+                }
+            }
+            Statement::Data(values) => {
+                for value in values {
+                    program.push(resolve(line, &value, &labels)?);
+                }
+            }
+        }
+    }
+
+    Ok(program)
+}
+
+/// Assemble the given source into the comma separated format accepted by
+/// `IntCodeVM::from_str`.
+pub fn assemble_to_string(source: &str) -> Result<String, AssemblyError> {
+    let program = assemble(source)?
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>();
+
+    Ok(program.join(","))
+}
+
+fn parse_statement(line: usize, text: &str) -> Result<Statement, AssemblyError> {
+    let (head, rest) = match text.find(char::is_whitespace) {
+        Some(pos) => (&text[..pos], text[pos..].trim()),
+        None => (text, ""),
+    };
+
+    let operands = rest
+        .replace("->", ",")
+        .split(',')
+        .map(str::trim)
+        .filter(|operand| !operand.is_empty())
+        .map(String::from)
+        .collect::<Vec<_>>();
+
+    if head.eq_ignore_ascii_case(".data") {
+        let values = operands
+            .iter()
+            .map(|operand| {
+                parse_expr(operand).ok_or_else(|| AssemblyError::InvalidOperand {
+                    line,
+                    operand: operand.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        return Ok(Statement::Data(values));
+    }
+
+    let code = OpCode::from_mnemonic(head).ok_or_else(|| AssemblyError::UnknownMnemonic {
+        line,
+        mnemonic: head.to_string(),
+    })?;
+
+    if operands.len() != code.parameter_count() {
+        return Err(AssemblyError::WrongOperandCount {
+            line,
+            expected: code.parameter_count(),
+            found: operands.len(),
+        });
+    }
+
+    let operands = operands
+        .iter()
+        .map(|operand| {
+            parse_operand(operand).ok_or_else(|| AssemblyError::InvalidOperand {
+                line,
+                operand: operand.clone(),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if code.writes_result() {
+        if let Some(Operand::Immediate(_)) = operands.last() {
+            return Err(AssemblyError::ImmediateWrite { line });
+        }
+    }
+
+    Ok(Statement::Instruction(code, operands))
+}
+
+fn parse_operand(operand: &str) -> Option<Operand> {
+    if let Some(expr) = operand.strip_prefix('#') {
+        Some(Operand::Immediate(parse_expr(expr)?))
+    } else if operand.starts_with('[') && operand.ends_with(']') {
+        Some(Operand::Positional(parse_expr(
+            &operand[1..operand.len() - 1],
+        )?))
+    } else if let Some(offset) = operand.strip_prefix("rel") {
+        let offset = offset.replace(' ', "");
+
+        match offset.as_str() {
+            "" => Some(Operand::Relative(0)),
+            _ => Some(Operand::Relative(
+                offset.trim_start_matches('+').parse().ok()?,
+            )),
+        }
+    } else {
+        None
+    }
+}
+
+fn parse_expr(expr: &str) -> Option<Expr> {
+    let expr = expr.replace(' ', "");
+
+    if let Ok(offset) = expr.parse() {
+        return Some(Expr {
+            label: None,
+            offset,
+        });
+    }
+
+    let (label, offset) = match expr.find(['+', '-']) {
+        Some(pos) => (
+            &expr[..pos],
+            expr[pos..].trim_start_matches('+').parse().ok()?,
+        ),
+        None => (expr.as_str(), 0),
+    };
+
+    if !is_label(label) {
+        return None;
+    }
+
+    Some(Expr {
+        label: Some(label.to_string()),
+        offset,
+    })
+}
+
+fn resolve(
+    line: usize,
+    expr: &Expr,
+    labels: &HashMap<String, usize>,
+) -> Result<i64, AssemblyError> {
+    match &expr.label {
+        None => Ok(expr.offset),
+        Some(label) => match labels.get(label) {
+            Some(&address) => std::convert::TryFrom::try_from(address)
+                .ok()
+                .and_then(|address: i64| address.checked_add(expr.offset))
+                .ok_or_else(|| AssemblyError::OffsetOverflow {
+                    line,
+                    label: label.clone(),
+                    offset: expr.offset,
+                }),
+            None => Err(AssemblyError::UnknownLabel {
+                line,
+                label: label.clone(),
+            }),
+        },
+    }
+}
+
+fn is_label(text: &str) -> bool {
+    let mut chars = text.chars();
+
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+
+    text != "rel" && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::intcode::asm::*;
+    use crate::intcode::disasm::disassemble;
+    use crate::intcode::IntCodeVM;
+
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_assemble() {
+        let source = "
+            ; double the input until it exceeds 100
+            start:  IN -> [value]
+            loop:   MUL [value], #2 -> [value]
+                    LT [value], #100, [flag]
+                    JT [flag], #loop
+                    OUT [value]
+                    HLT
+            value:  .data 0
+            flag:   .data 0
+        ";
+
+        assert_eq!(
+            "3,16,1002,16,2,16,1007,16,100,17,1005,17,2,4,16,99,0,0",
+            assemble_to_string(source).unwrap()
+        );
+
+        let mut vm: IntCodeVM = assemble_to_string(source).unwrap().parse().unwrap();
+        let mut output = Vec::new();
+        vm.run(&mut VecDeque::from(vec![7]), &mut output).unwrap();
+
+        assert_eq!(vec![112], output);
+    }
+
+    #[test]
+    fn test_assemble_relative_and_offsets() {
+        assert_eq!(
+            vec![109, 4, 22201, -1, 1, 2, 1001, 11, 1, 12, 99, 5, 0],
+            assemble(
+                "ARB #4
+                 ADD rel-1, rel+1 -> rel+2
+                 ADD [data], #1 -> [data+1]
+                 HLT
+                 data: .data 5, 0"
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_assemble_disassembly() {
+        let program = vec![1101, 5, 6, 12, 1105, 1, 9, 1, 1, 4, 12, 99, 0, 7, 7];
+        let listing = disassemble(&program).to_string();
+
+        assert_eq!(program, assemble(&listing).unwrap());
+    }
+
+    #[test]
+    fn test_assemble_errors() {
+        assert_eq!(
+            Err(AssemblyError::UnknownMnemonic {
+                line: 1,
+                mnemonic: "JMP".to_string()
+            }),
+            assemble("JMP #0")
+        );
+        assert_eq!(
+            Err(AssemblyError::WrongOperandCount {
+                line: 2,
+                expected: 3,
+                found: 2
+            }),
+            assemble("HLT\nADD #1, #2")
+        );
+        assert_eq!(
+            Err(AssemblyError::ImmediateWrite { line: 1 }),
+            assemble("ADD #1, #2 -> #3")
+        );
+        assert_eq!(
+            Err(AssemblyError::UnknownLabel {
+                line: 1,
+                label: "nowhere".to_string()
+            }),
+            assemble("JT #1, #nowhere")
+        );
+        assert_eq!(
+            Err(AssemblyError::DuplicateLabel {
+                line: 2,
+                label: "a".to_string()
+            }),
+            assemble("a: HLT\na: HLT")
+        );
+        assert_eq!(
+            Err(AssemblyError::AddressMismatch {
+                line: 2,
+                expected: 3,
+                found: 1
+            }),
+            assemble("0000: HLT\n0003: HLT")
+        );
+        assert_eq!(
+            Err(AssemblyError::InvalidAddress {
+                line: 1,
+                address: "99999999999999999999999".to_string()
+            }),
+            assemble("99999999999999999999999: HLT")
+        );
+
+        let err = assemble("HLT\nx: .data x+9223372036854775807").unwrap_err();
+        assert_eq!(
+            AssemblyError::OffsetOverflow {
+                line: 2,
+                label: "x".to_string(),
+                offset: i64::MAX
+            },
+            err
+        );
+        assert_eq!(
+            "line 2: x+9223372036854775807 is out of range",
+            err.to_string()
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use crate::intcode::{IntCodeError, IntCodeVM, Status, Word};
+
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Number of instructions `IntCodeVM::run_async` executes before it yields to the
+/// executor, so long computations without I/O don't starve other tasks.
+pub const ASYNC_SLICE: u64 = 10_000;
+
+/// Why `IntCodeVM::run_async` failed.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum AsyncError<E> {
+    IntCode(IntCodeError),
+    /// The output sink failed to accept a value.
+    Output(E),
+}
+
+impl<E: Display> Display for AsyncError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AsyncError::IntCode(e) => write!(f, "{}", e),
+            AsyncError::Output(e) => write!(f, "failed to write output: {}", e),
+        }
+    }
+}
+
+impl<E: Debug + Display> Error for AsyncError<E> {}
+
+impl<E> From<IntCodeError> for AsyncError<E> {
+    fn from(e: IntCodeError) -> Self {
+        AsyncError::IntCode(e)
+    }
+}
+
+impl<W: Word> IntCodeVM<W> {
+    /// Like `run`, but awaits every input value from `input` and sends every output
+    /// value to `output`, so the VM can run alongside other tasks without blocking a
+    /// thread. Values queued by `push_input` are read first. Fails with
+    /// `IntCodeError::InputExhausted` if the stream ends while the program waits for
+    /// input. Yields after every `ASYNC_SLICE` instructions.
+    pub async fn run_async<I, O>(
+        &mut self,
+        input: &mut I,
+        output: &mut O,
+    ) -> Result<(), AsyncError<O::Error>>
+    where
+        I: Stream<Item = W> + Unpin,
+        O: Sink<W> + Unpin,
+    {
+        loop {
+            let status = match self.resume_limited(ASYNC_SLICE)? {
+                Some(status) => status,
+                None => {
+                    YieldNow(false).await;
+                    continue;
+                }
+            };
+
+            match status {
+                Status::NeedsInput => match input.next().await {
+                    Some(value) => self.push_input(value),
+                    None => {
+                        return Err(AsyncError::IntCode(IntCodeError::InputExhausted {
+                            ptr: self.ptr(),
+                            instruction: self.current_instruction(),
+                        }))
+                    }
+                },
+                Status::Output(value) => output.send(value).await.map_err(AsyncError::Output)?,
+                Status::Halted => return Ok(()),
+            }
+        }
+    }
+}
+
+/// Returns pending once after waking its task, so the executor can run others first.
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+
+        self.0 = true;
+        cx.waker().wake_by_ref();
+
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::intcode::*;
+
+    use futures::channel::mpsc;
+    use futures::executor::block_on;
+    use futures::{stream, SinkExt, StreamExt};
+    use std::cell::Cell;
+
+    #[test]
+    fn test_vm_run_async() {
+        let mut vm: IntCodeVM = "3,0,4,0,3,0,4,0,99".parse().unwrap();
+        vm.push_input(4);
+        let mut output = Vec::new();
+
+        block_on(vm.run_async(&mut stream::iter(vec![2]), &mut output)).unwrap();
+        assert_eq!(vec![4, 2], output);
+        assert!(vm.is_halted());
+
+        let mut vm: IntCodeVM = "3,0,4,0,3,0,4,0,99".parse().unwrap();
+        let err = block_on(vm.run_async(&mut stream::iter(vec![1]), &mut Vec::new())).unwrap_err();
+        assert_eq!(
+            "no input available in instruction 3 (IN) at position 4",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_vm_run_async_yields() {
+        // counts down from the input, far longer than a single slice
+        let mut vm: IntCodeVM = "3,11,1001,11,-1,11,1005,11,2,99,0,0".parse().unwrap();
+        let done = Cell::new(false);
+
+        let vm = async {
+            let result = vm
+                .run_async(&mut stream::iter(vec![100_000]), &mut Vec::new())
+                .await;
+            done.set(true);
+            result
+        };
+        // polled right after the VM's first slice
+        let other = async { done.get() };
+        let (result, vm_was_done) = block_on(async { futures::join!(vm, other) });
+
+        result.unwrap();
+        assert!(!vm_was_done);
+    }
+
+    #[test]
+    fn test_vm_run_async_with_controller() {
+        // doubles every input until it reads 0
+        let mut vm: IntCodeVM = "3,15,1006,15,14,102,2,15,15,4,15,1105,1,0,99,0"
+            .parse()
+            .unwrap();
+        let (mut commands, mut input) = mpsc::channel(1);
+        let (mut output, mut results) = mpsc::channel(1);
+
+        // answers every result with the next command, like a remote controller would
+        let controller = async move {
+            let mut received = Vec::new();
+            commands.send(1).await.unwrap();
+
+            while let Some(value) = results.next().await {
+                received.push(value);
+                commands
+                    .send(if value < 8 { value } else { 0 })
+                    .await
+                    .unwrap();
+            }
+
+            received
+        };
+
+        // the controller finishes once the VM dropped its end of the channel
+        let vm = async move { vm.run_async(&mut input, &mut output).await };
+        let (result, received) = block_on(async { futures::join!(vm, controller) });
+
+        result.unwrap();
+        assert_eq!(vec![2, 4, 8], received);
+    }
+}
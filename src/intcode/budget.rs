@@ -0,0 +1,347 @@
+use crate::intcode::trace::{Step, TracerFn};
+use crate::intcode::{Input, IntCodeError, IntCodeVM, Memory, OpCode, Output, Word};
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+/// Limits for `IntCodeVM::run_with_budget`. Nothing is limited by default.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Budget {
+    max_steps: Option<u64>,
+    max_time: Option<Duration>,
+    max_memory: Option<usize>,
+    detect_loops: bool,
+}
+
+impl Budget {
+    /// Fail with `IntCodeError::StepLimitExceeded` after `steps` instructions.
+    pub fn with_max_steps(mut self, steps: u64) -> Self {
+        self.max_steps = Some(steps);
+        self
+    }
+
+    /// Fail with `IntCodeError::TimeLimitExceeded` once the run took longer than `time`.
+    pub fn with_max_time(mut self, time: Duration) -> Self {
+        self.max_time = Some(time);
+        self
+    }
+
+    /// Fail with `IntCodeError::MemoryLimitExceeded` before an instruction would
+    /// allocate more than `cells` memory cells, see `Memory::len`.
+    pub fn with_max_memory(mut self, cells: usize) -> Self {
+        self.max_memory = Some(cells);
+        self
+    }
+
+    /// Fail with `IntCodeError::InfiniteLoop` if the VM returns to a previous state
+    /// without input or output in between. Loops are found with Brent's algorithm,
+    /// so only a single copy of the state is kept, and a loop is reported within a
+    /// few times its length once `LOOP_DETECTION_START` steps passed without I/O.
+    pub fn with_loop_detection(mut self) -> Self {
+        self.detect_loops = true;
+        self
+    }
+}
+
+impl<W: Word> IntCodeVM<W> {
+    /// Like `run`, but stops with an error as soon as the budget is exhausted.
+    pub fn run_with_budget(
+        &mut self,
+        input: &mut dyn Input<W>,
+        output: &mut dyn Output<W>,
+        budget: &Budget,
+    ) -> Result<(), IntCodeError> {
+        let start = Instant::now();
+        let mut detector = if budget.detect_loops {
+            Some(LoopDetector::new(self))
+        } else {
+            None
+        };
+        let mut steps = 0;
+
+        loop {
+            if let Some(limit) = budget.max_steps {
+                if steps >= limit {
+                    return Err(IntCodeError::StepLimitExceeded {
+                        ptr: self.ptr(),
+                        instruction: self.current_instruction(),
+                        limit,
+                    });
+                }
+            }
+
+            if let Some(limit) = budget.max_time {
+                if start.elapsed() > limit {
+                    return Err(IntCodeError::TimeLimitExceeded {
+                        ptr: self.ptr(),
+                        instruction: self.current_instruction(),
+                        limit,
+                    });
+                }
+            }
+
+            if let Some(limit) = budget.max_memory {
+                if self
+                    .next_write()
+                    .is_some_and(|address| self.memory().len_after_write(address) > limit)
+                {
+                    return Err(IntCodeError::MemoryLimitExceeded {
+                        ptr: self.ptr(),
+                        instruction: self.current_instruction(),
+                        limit,
+                    });
+                }
+            }
+
+            let running = match &mut detector {
+                Some(detector) => {
+                    let mut executed = None;
+                    let running = self.run_instruction_traced(
+                        input,
+                        output,
+                        &mut TracerFn(|step| executed = Some(step)),
+                    )?;
+
+                    if let Some(step) = executed {
+                        if running && detector.repeated(self, &step) {
+                            return Err(IntCodeError::InfiniteLoop {
+                                ptr: self.ptr(),
+                                instruction: self.current_instruction(),
+                            });
+                        }
+                    }
+
+                    running
+                }
+                None => self.run_instruction(input, output)?,
+            };
+
+            if !running {
+                return Ok(());
+            }
+
+            steps += 1;
+        }
+    }
+
+    // the address the next instruction writes to, None if it doesn't write or
+    // fails anyway
+    fn next_write(&self) -> Option<usize> {
+        let instruction = self.current_instruction();
+        let parameter = match instruction.code() {
+            OpCode::Add | OpCode::Multiply | OpCode::LessThan | OpCode::Equals => 2,
+            OpCode::Input => 0,
+            _ => return None,
+        };
+
+        self.write_address(
+            self.ptr() + 1 + parameter,
+            instruction.get_param_mode(parameter),
+        )
+        .ok()
+    }
+}
+
+/// Number of steps without input or output before the loop detection saves the
+/// first state. Saving copies the memory, programs that do I/O regularly never pay
+/// for it.
+pub const LOOP_DETECTION_START: u64 = 1024;
+
+/// Brent's cycle detection over the states since the last input or output. The
+/// state is saved whenever the step count reaches a power of two and every later
+/// state is compared with it, by hash first and exactly if the hashes match.
+struct LoopDetector<W> {
+    // xor of the hashes of all non-zero cells
+    memory: u64,
+    saved: Option<State<W>>,
+    power: u64,
+    steps: u64,
+}
+
+struct State<W> {
+    memory_hash: u64,
+    ptr: usize,
+    relative_base: W,
+    memory: Memory<W>,
+}
+
+impl<W: Word> LoopDetector<W> {
+    fn new(vm: &IntCodeVM<W>) -> Self {
+        let memory = vm
+            .memory()
+            .cells()
+            .map(|(address, cell)| cell_hash(address, cell))
+            .fold(0, |hash, cell| hash ^ cell);
+
+        LoopDetector {
+            memory,
+            saved: None,
+            power: LOOP_DETECTION_START,
+            steps: 0,
+        }
+    }
+
+    // update the state with the executed step, returns true if the resulting state
+    // was seen before
+    fn repeated(&mut self, vm: &IntCodeVM<W>, step: &Step<W>) -> bool {
+        for write in &step.writes {
+            self.memory ^=
+                cell_hash(write.address, &write.old) ^ cell_hash(write.address, &write.new);
+        }
+
+        if step.input().is_some() || step.output.is_some() {
+            self.saved = None;
+            self.power = LOOP_DETECTION_START;
+            self.steps = 0;
+
+            return false;
+        }
+
+        self.steps += 1;
+
+        if let Some(saved) = &self.saved {
+            if saved.matches(self.memory, vm) {
+                return true;
+            }
+        }
+
+        if self.steps == self.power {
+            self.saved = Some(State {
+                memory_hash: self.memory,
+                ptr: vm.ptr(),
+                relative_base: vm.relative_base().clone(),
+                memory: vm.memory().clone(),
+            });
+            self.power *= 2;
+            self.steps = 0;
+        }
+
+        false
+    }
+}
+
+impl<W: Word> State<W> {
+    // memory that only grew by zeroes is the same state
+    fn matches(&self, memory_hash: u64, vm: &IntCodeVM<W>) -> bool {
+        let non_zero = |(_, value): &(usize, &W)| **value != W::zero();
+
+        self.memory_hash == memory_hash
+            && self.ptr == vm.ptr()
+            && &self.relative_base == vm.relative_base()
+            && self
+                .memory
+                .cells()
+                .filter(non_zero)
+                .eq(vm.memory().cells().filter(non_zero))
+    }
+}
+
+// zero cells hash to zero, so memory that grew by zeroes hashes the same
+fn cell_hash<W: Word>(address: usize, value: &W) -> u64 {
+    if *value == W::zero() {
+        return 0;
+    }
+
+    let mut hasher = DefaultHasher::new();
+    address.hash(&mut hasher);
+    value.hash(&mut hasher);
+
+    hasher.finish()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::intcode::budget::*;
+    use crate::intcode::Instruction;
+
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_budget_steps_and_time() {
+        let mut vm: IntCodeVM = "1105,1,0".parse().unwrap();
+        let budget = Budget::default().with_max_time(Duration::from_millis(10));
+
+        assert_eq!(
+            Err(IntCodeError::TimeLimitExceeded {
+                ptr: 0,
+                instruction: Instruction::from_int(1105),
+                limit: Duration::from_millis(10)
+            }),
+            vm.run_with_budget(&mut (), &mut (), &budget)
+        );
+
+        let budget = Budget::default().with_max_steps(5);
+        let mut vm: IntCodeVM = "1,0,0,0,1,0,0,0,99".parse().unwrap();
+        assert_eq!(
+            Ok(()),
+            vm.clone().run_with_budget(&mut (), &mut (), &budget)
+        );
+
+        let budget = Budget::default().with_max_steps(2);
+        assert_eq!(
+            Err(IntCodeError::StepLimitExceeded {
+                ptr: 8,
+                instruction: Instruction::from_int(99),
+                limit: 2
+            }),
+            vm.run_with_budget(&mut (), &mut (), &budget)
+        );
+    }
+
+    #[test]
+    fn test_budget_memory() {
+        let mut vm: IntCodeVM = "1101,1,1,5000,99".parse().unwrap();
+        let budget = Budget::default().with_max_memory(1000);
+
+        assert_eq!(
+            Err(IntCodeError::MemoryLimitExceeded {
+                ptr: 0,
+                instruction: Instruction::from_int(1101),
+                limit: 1000
+            }),
+            vm.run_with_budget(&mut (), &mut (), &budget)
+        );
+        assert_eq!(5, vm.memory().len());
+
+        let budget = Budget::default().with_max_memory(5001);
+        assert_eq!(Ok(()), vm.run_with_budget(&mut (), &mut (), &budget));
+    }
+
+    #[test]
+    fn test_budget_loop_detection() {
+        let budget = Budget::default().with_loop_detection();
+
+        // jumps back and forth between two instructions
+        let mut vm: IntCodeVM = "1105,1,3,1105,1,0".parse().unwrap();
+        assert_eq!(
+            Err(IntCodeError::InfiniteLoop {
+                ptr: 0,
+                instruction: Instruction::from_int(1105)
+            }),
+            vm.run_with_budget(&mut (), &mut (), &budget)
+        );
+
+        // negates a cell on every iteration, so the state repeats every other one
+        let mut vm: IntCodeVM = "1002,7,-1,7,1105,1,0,1".parse().unwrap();
+        assert!(matches!(
+            vm.run_with_budget(&mut (), &mut (), &budget),
+            Err(IntCodeError::InfiniteLoop { .. })
+        ));
+
+        // counts down from the input, the state changes on every iteration
+        let mut vm: IntCodeVM = "3,12,1001,12,-1,12,1005,12,2,4,12,99,0".parse().unwrap();
+        let mut output = Vec::new();
+        vm.run_with_budget(&mut VecDeque::from(vec![1000]), &mut output, &budget)
+            .unwrap();
+        assert_eq!(vec![0], output);
+
+        // prints forever, output between identical states is not a loop
+        let mut vm: IntCodeVM = "4,0,1105,1,0".parse().unwrap();
+        let budget = budget.with_max_steps(100);
+        assert!(matches!(
+            vm.run_with_budget(&mut (), &mut Vec::new(), &budget),
+            Err(IntCodeError::StepLimitExceeded { .. })
+        ));
+    }
+}
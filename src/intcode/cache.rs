@@ -0,0 +1,98 @@
+use crate::intcode::{Instruction, Memory, Word};
+
+/// Instructions at addresses beyond this limit are decoded on every execution, so
+/// programs jumping around in huge paged memory don't blow up the cache.
+const CACHE_LIMIT: usize = 1 << 16;
+
+/// Decoded instructions by address. Only the instruction cell itself is cached,
+/// parameters are always read from memory, so a write has to invalidate nothing
+/// but the written address.
+#[derive(Debug, Clone)]
+pub(crate) struct DecodeCache {
+    enabled: bool,
+    entries: Vec<Option<Instruction>>,
+}
+
+impl DecodeCache {
+    pub fn new() -> Self {
+        DecodeCache {
+            enabled: true,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.entries.clear();
+    }
+
+    /// Decode the instruction at the given address. Returns None if the address is
+    /// out of bounds.
+    pub fn get<W: Word>(&mut self, memory: &Memory<W>, address: usize) -> Option<Instruction> {
+        if !self.enabled || address >= CACHE_LIMIT {
+            return memory
+                .get(address)
+                .map(|word| Instruction::from_word(&word));
+        }
+
+        if address >= self.entries.len() {
+            self.entries.resize(address + 1, None);
+        }
+
+        if let Some(instruction) = self.entries[address] {
+            return Some(instruction);
+        }
+
+        let instruction = Instruction::from_word(&memory.get(address)?);
+        self.entries[address] = Some(instruction);
+
+        Some(instruction)
+    }
+
+    /// Forget the instruction at the given address after it was written.
+    pub fn invalidate(&mut self, address: usize) {
+        if let Some(entry) = self.entries.get_mut(address) {
+            *entry = None;
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::intcode::cache::*;
+    use crate::intcode::{IntCodeVM, OpCode};
+
+    #[test]
+    fn test_cache_invalidation() {
+        let mut memory = Memory::dense(vec![1, 0, 0, 0, 99]);
+        let mut cache = DecodeCache::new();
+
+        assert_eq!(OpCode::Add, cache.get(&memory, 0).unwrap().code());
+
+        assert!(memory.set(0, 2));
+        assert_eq!(OpCode::Add, cache.get(&memory, 0).unwrap().code());
+
+        cache.invalidate(0);
+        assert_eq!(OpCode::Multiply, cache.get(&memory, 0).unwrap().code());
+        assert_eq!(
+            None,
+            cache.get(&Memory::<i64>::dense(vec![]).with_max_size(1), 1)
+        );
+    }
+
+    #[test]
+    fn test_cache_self_modifying_code() {
+        // adds 3 to the value and prints it, then turns the add into a multiplication
+        // and runs it again
+        let program = "1001,21,3,21,4,21,1005,22,20,1101,1,1001,0,1101,0,1,22,1105,1,0,99,5,0";
+
+        for cached in &[true, false] {
+            let mut vm: IntCodeVM = program.parse().unwrap();
+            vm.set_decode_cache(*cached);
+            let mut output = Vec::new();
+            vm.run(&mut (), &mut output).unwrap();
+
+            assert_eq!(vec![8, 24], output);
+        }
+    }
+}
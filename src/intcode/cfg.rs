@@ -0,0 +1,392 @@
+//! Static control-flow graph of an IntCode program.
+
+use crate::intcode::disasm::{decode, reachable, successors, Line, Operand};
+use crate::intcode::{OpCode, ParamMode, Word};
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+use std::ops::Range;
+
+/// An edge to the block starting at the given address.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Edge {
+    /// Execution continues with the next instruction.
+    Next(usize),
+    /// A jump with an immediate destination.
+    Jump(usize),
+}
+
+impl Edge {
+    pub fn target(&self) -> usize {
+        match self {
+            Edge::Next(target) | Edge::Jump(target) => *target,
+        }
+    }
+}
+
+/// A run of instructions that is only entered at the first and only left after the
+/// last instruction.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BasicBlock<W> {
+    pub start: usize,
+    /// The address after the last parameter of the last instruction.
+    pub end: usize,
+    pub lines: Vec<Line<W>>,
+    pub edges: Vec<Edge>,
+    /// The block ends with a jump whose destination is only known at runtime.
+    pub dynamic_jump: bool,
+}
+
+/// An instruction writing to a cell that holds reachable code.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct CodeWrite {
+    /// Address of the writing instruction.
+    pub ptr: usize,
+    /// The written address.
+    pub address: usize,
+}
+
+/// The graph of all instructions reachable from address zero, see `disasm::reachable`.
+/// Writes are only resolved for positional destinations.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ControlFlowGraph<W> {
+    pub blocks: BTreeMap<usize, BasicBlock<W>>,
+    /// Ranges of cells that are not part of any reachable instruction.
+    pub unreachable: Vec<Range<usize>>,
+    /// Writes to code, i.e. the self-modifying parts of the program.
+    pub code_writes: Vec<CodeWrite>,
+}
+
+impl<W: Word> ControlFlowGraph<W> {
+    pub fn new(program: &[W]) -> Self {
+        let starts = reachable(program);
+        let decoded = starts
+            .iter()
+            .map(|&address| (address, decode(program, address).unwrap()))
+            .collect::<BTreeMap<_, _>>();
+
+        // blocks start at the entry point, at jump destinations and after jumps
+        let mut leaders = BTreeSet::new();
+        leaders.insert(0);
+
+        for (&address, (instruction, operands)) in &decoded {
+            if ends_block(instruction.code()) {
+                leaders.extend(successors(address, instruction, operands));
+                leaders.insert(address + 1 + operands.len());
+            }
+        }
+
+        let mut blocks = BTreeMap::new();
+        let mut current: Option<BasicBlock<W>> = None;
+
+        for (address, (instruction, operands)) in decoded {
+            let next = address + 1 + operands.len();
+
+            let mut block = match current.take() {
+                Some(block) if block.end == address && !leaders.contains(&address) => block,
+                previous => {
+                    if let Some(mut block) = previous {
+                        if starts.contains(&block.end) {
+                            block.edges.push(Edge::Next(block.end));
+                        }
+
+                        blocks.insert(block.start, block);
+                    }
+
+                    BasicBlock {
+                        start: address,
+                        end: address,
+                        lines: Vec::new(),
+                        edges: Vec::new(),
+                        dynamic_jump: false,
+                    }
+                }
+            };
+
+            block.end = next;
+
+            if ends_block(instruction.code()) {
+                block.edges = successors(address, &instruction, &operands)
+                    .into_iter()
+                    .filter(|target| starts.contains(target))
+                    .map(|target| {
+                        if target == next {
+                            Edge::Next(target)
+                        } else {
+                            Edge::Jump(target)
+                        }
+                    })
+                    .collect();
+                block.dynamic_jump = is_dynamic_jump(instruction.code(), &operands);
+            }
+
+            block.lines.push(Line::Code {
+                address,
+                instruction,
+                operands,
+            });
+
+            if ends_block(instruction.code()) {
+                blocks.insert(block.start, block);
+            } else {
+                current = Some(block);
+            }
+        }
+
+        if let Some(mut block) = current {
+            if starts.contains(&block.end) {
+                block.edges.push(Edge::Next(block.end));
+            }
+
+            blocks.insert(block.start, block);
+        }
+
+        let unreachable = unreachable(program.len(), &blocks);
+        let code_writes = code_writes(&blocks);
+
+        ControlFlowGraph {
+            blocks,
+            unreachable,
+            code_writes,
+        }
+    }
+
+    /// The block containing the given address.
+    pub fn block_at(&self, address: usize) -> Option<&BasicBlock<W>> {
+        self.blocks
+            .range(..=address)
+            .next_back()
+            .map(|(_, block)| block)
+            .filter(|block| address < block.end)
+    }
+
+    /// Write the graph in the Graphviz DOT format. Blocks written by the program are
+    /// highlighted and connected to the writing block by a dashed edge.
+    pub fn write_dot<O: Write>(&self, mut out: O) -> io::Result<()> {
+        writeln!(out, "digraph intcode {{")?;
+        writeln!(out, "    node [shape=box, fontname=\"monospace\"];")?;
+
+        for block in self.blocks.values() {
+            let label = block
+                .lines
+                .iter()
+                .map(|line| format!("{}\\l", escape(&line.to_string())))
+                .collect::<String>();
+            let style = if self
+                .code_writes
+                .iter()
+                .any(|write| block.start <= write.address && write.address < block.end)
+            {
+                ", style=filled, fillcolor=\"#f4cccc\""
+            } else {
+                ""
+            };
+
+            writeln!(out, "    b{} [label=\"{}\"{}];", block.start, label, style)?;
+        }
+
+        for range in &self.unreachable {
+            writeln!(
+                out,
+                "    u{} [label=\"{:04}..{:04} unreachable\", style=dotted];",
+                range.start,
+                range.start,
+                range.end - 1
+            )?;
+        }
+
+        if self.blocks.values().any(|block| block.dynamic_jump) {
+            writeln!(out, "    dynamic [label=\"?\", shape=circle];")?;
+        }
+
+        for block in self.blocks.values() {
+            for edge in &block.edges {
+                let label = match edge {
+                    Edge::Next(_) => "",
+                    Edge::Jump(_) => " [label=\"jump\"]",
+                };
+
+                writeln!(out, "    b{} -> b{}{};", block.start, edge.target(), label)?;
+            }
+
+            if block.dynamic_jump {
+                writeln!(out, "    b{} -> dynamic [style=dashed];", block.start)?;
+            }
+        }
+
+        for write in &self.code_writes {
+            if let (Some(from), Some(to)) = (self.block_at(write.ptr), self.block_at(write.address))
+            {
+                writeln!(
+                    out,
+                    "    b{} -> b{} [style=dashed, color=red, label=\"writes {}\"];",
+                    from.start, to.start, write.address
+                )?;
+            }
+        }
+
+        writeln!(out, "}}")
+    }
+}
+
+fn ends_block(code: OpCode) -> bool {
+    matches!(
+        code,
+        OpCode::JumpIfTrue | OpCode::JumpIfFalse | OpCode::Exit
+    )
+}
+
+// a jump that may be taken but whose destination isn't an immediate value
+fn is_dynamic_jump<W: Word>(code: OpCode, operands: &[Operand<W>]) -> bool {
+    let jumps_if_true = match code {
+        OpCode::JumpIfTrue => true,
+        OpCode::JumpIfFalse => false,
+        _ => return false,
+    };
+    let (condition, destination) = (&operands[0], &operands[1]);
+    let never_taken =
+        condition.mode == ParamMode::Immediate && (condition.value != W::zero()) != jumps_if_true;
+
+    destination.mode != ParamMode::Immediate && !never_taken
+}
+
+// coalesce the cells outside of all blocks into ranges
+fn unreachable<W>(len: usize, blocks: &BTreeMap<usize, BasicBlock<W>>) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut address = 0;
+
+    for block in blocks.values() {
+        if address < block.start {
+            ranges.push(address..block.start);
+        }
+
+        address = block.end;
+    }
+
+    if address < len {
+        ranges.push(address..len);
+    }
+
+    ranges
+}
+
+fn code_writes<W: Word>(blocks: &BTreeMap<usize, BasicBlock<W>>) -> Vec<CodeWrite> {
+    let is_code = |address: usize| {
+        blocks
+            .range(..=address)
+            .next_back()
+            .is_some_and(|(_, block)| address < block.end)
+    };
+
+    blocks
+        .values()
+        .flat_map(|block| &block.lines)
+        .filter_map(|line| match line {
+            Line::Code {
+                address,
+                instruction,
+                operands,
+            } if instruction.code().writes_result() => {
+                let destination = operands.last()?;
+
+                if destination.mode != ParamMode::Positional {
+                    return None;
+                }
+
+                let target = destination
+                    .value
+                    .to_i64()
+                    .and_then(|d| std::convert::TryFrom::try_from(d).ok())?;
+
+                if is_code(target) {
+                    Some(CodeWrite {
+                        ptr: *address,
+                        address: target,
+                    })
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::intcode::cfg::*;
+
+    #[test]
+    fn test_cfg_blocks() {
+        // counts down from 3, printing every value, then halts
+        //   0: ADD #3, #0 -> [16]
+        //   4: OUT [16]
+        //   6: ADD [16], #-1 -> [16]
+        //  10: JT [16], #4
+        //  13: HLT
+        //  14: .data 0, 0, 0
+        let program = vec![
+            1101, 3, 0, 16, 4, 16, 1001, 16, -1, 16, 1005, 16, 4, 99, 0, 0, 0,
+        ];
+        let cfg = ControlFlowGraph::new(&program);
+
+        assert_eq!(
+            vec![0, 4, 13],
+            cfg.blocks.keys().copied().collect::<Vec<_>>()
+        );
+        assert_eq!(vec![Edge::Next(4)], cfg.blocks[&0].edges);
+        assert_eq!(vec![Edge::Next(13), Edge::Jump(4)], cfg.blocks[&4].edges);
+        assert_eq!(Vec::<Edge>::new(), cfg.blocks[&13].edges);
+        assert_eq!(13, cfg.blocks[&4].end);
+        assert_eq!(vec![14..17], cfg.unreachable);
+        assert!(cfg.code_writes.is_empty());
+        assert_eq!(Some(4), cfg.block_at(11).map(|block| block.start));
+        assert_eq!(None, cfg.block_at(14));
+    }
+
+    #[test]
+    fn test_cfg_self_modifying_and_dynamic_jumps() {
+        // see the decode cache tests, the add at 13 turns the add at 0 into a
+        // multiplication
+        let program = vec![
+            1001, 21, 3, 21, 4, 21, 1005, 22, 20, 1101, 1, 1001, 0, 1101, 0, 1, 22, 1105, 1, 0, 99,
+            5, 0,
+        ];
+        let cfg = ControlFlowGraph::new(&program);
+
+        assert_eq!(
+            vec![0, 9, 20],
+            cfg.blocks.keys().copied().collect::<Vec<_>>()
+        );
+        assert_eq!(vec![Edge::Next(9), Edge::Jump(20)], cfg.blocks[&0].edges);
+        assert_eq!(vec![Edge::Jump(0)], cfg.blocks[&9].edges);
+        assert_eq!(vec![CodeWrite { ptr: 9, address: 0 }], cfg.code_writes);
+
+        // JT [3], [4] and JF #1, [3], which is never taken
+        assert!(ControlFlowGraph::new(&[5, 3, 4, 0, 99]).blocks[&0].dynamic_jump);
+        assert!(!ControlFlowGraph::new(&[106, 1, 3, 99]).blocks[&0].dynamic_jump);
+    }
+
+    #[test]
+    fn test_cfg_dot() {
+        let program = vec![1105, 1, 4, 7, 1101, 1, 1, 0, 99];
+        let mut dot = Vec::new();
+        ControlFlowGraph::new(&program).write_dot(&mut dot).unwrap();
+
+        assert_eq!(
+            "digraph intcode {\n    \
+                 node [shape=box, fontname=\"monospace\"];\n    \
+                 b0 [label=\"0000: JT #1, #4\\l\", style=filled, fillcolor=\"#f4cccc\"];\n    \
+                 b4 [label=\"0004: ADD #1, #1 -> [0]\\l0008: HLT\\l\"];\n    \
+                 u3 [label=\"0003..0003 unreachable\", style=dotted];\n    \
+                 b0 -> b4 [label=\"jump\"];\n    \
+                 b4 -> b0 [style=dashed, color=red, label=\"writes 0\"];\n\
+             }\n",
+            String::from_utf8(dot).unwrap()
+        );
+    }
+}
@@ -0,0 +1,369 @@
+//! Ahead-of-time compilation of IntCode programs into closure-threaded code.
+//!
+//! Every statically reachable instruction is turned into a closure with its
+//! parameter modes resolved at compile time, so running it skips decoding and mode
+//! dispatch. Parameters are still read from memory when the instruction runs, so
+//! the compiled code stays valid as long as the opcode cells are unchanged: whenever
+//! the program is about to write to an opcode cell, jumps to an address that wasn't
+//! compiled or runs into anything unusual like missing input or an overflow,
+//! execution falls back to the interpreter, which then runs the program to the end
+//! and reports errors exactly like `IntCodeVM::run`.
+//!
+//! Patching parameters doesn't invalidate the code, e.g. a program compiled once
+//! runs with every noun and verb on day 2.
+
+use crate::intcode::disasm::{decode_memory, reachable_memory, Line};
+use crate::intcode::{Input, IntCodeError, IntCodeVM, OpCode, Output, ParamMode, Word};
+
+use std::collections::VecDeque;
+
+/// A compiled instruction, called with the VM, its I/O and the opcode cell map. Returns
+/// the address of the next instruction or None if the interpreter has to take over,
+/// in which case the VM is left untouched.
+type Op<W> =
+    Box<dyn Fn(&mut IntCodeVM<W>, &mut dyn Input<W>, &mut dyn Output<W>, &[bool]) -> Option<usize>>;
+
+/// The address of a parameter cell with the mode resolved at compile time.
+#[derive(Copy, Clone)]
+enum Arg {
+    Immediate(usize),
+    Positional(usize),
+    Relative(usize),
+}
+
+impl Arg {
+    fn read<W: Word>(self, vm: &IntCodeVM<W>) -> Option<W> {
+        match self {
+            Arg::Immediate(cell) => vm.read_int(cell).ok(),
+            _ => vm.read_int(self.target(vm)?).ok(),
+        }
+    }
+
+    fn target<W: Word>(self, vm: &IntCodeVM<W>) -> Option<usize> {
+        match self {
+            Arg::Positional(cell) => to_address(&vm.read_int(cell).ok()?),
+            Arg::Relative(cell) => relative_address(vm, &vm.read_int(cell).ok()?),
+            Arg::Immediate(_) => None,
+        }
+    }
+}
+
+/// A program compiled from the memory image of a VM.
+pub struct CompiledProgram<W: Word = i64> {
+    ops: Vec<Option<Op<W>>>,
+    // the opcode cell of every compiled instruction with its compiled value
+    code: Vec<(usize, W)>,
+    is_code: Vec<bool>,
+}
+
+impl<W: Word + 'static> CompiledProgram<W> {
+    /// Compile the instructions reachable from address zero.
+    pub fn new(vm: &IntCodeVM<W>) -> Self {
+        let memory = vm.memory();
+        let mut ops = Vec::new();
+        let mut code = Vec::new();
+
+        for address in reachable_memory(memory) {
+            let op = match compile(vm, address) {
+                Some(op) => op,
+                None => continue,
+            };
+
+            if ops.len() <= address {
+                ops.resize_with(address + 1, || None);
+            }
+
+            code.push((address, memory.get(address).unwrap()));
+            ops[address] = Some(op);
+        }
+
+        let is_code = ops.iter().map(Option::is_some).collect();
+
+        CompiledProgram { ops, code, is_code }
+    }
+
+    /// Number of compiled instructions.
+    pub fn len(&self) -> usize {
+        self.ops.iter().filter(|op| op.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns true if the opcode cells of the VM still hold the compiled program.
+    pub fn matches(&self, vm: &IntCodeVM<W>) -> bool {
+        self.code
+            .iter()
+            .all(|(address, value)| vm.read_int(*address).as_ref() == Ok(value))
+    }
+
+    /// Run the VM until it halts, like `IntCodeVM::run`. VMs whose code differs from
+    /// the compiled program are interpreted right away.
+    pub fn run(
+        &self,
+        vm: &mut IntCodeVM<W>,
+        input: &mut dyn Input<W>,
+        output: &mut dyn Output<W>,
+    ) -> Result<(), IntCodeError> {
+        if !vm.is_halted() && self.matches(vm) {
+            while let Some(op) = self.ops.get(vm.ptr()).and_then(Option::as_ref) {
+                match op(vm, input, output, &self.is_code) {
+                    Some(next) => vm.jmp(next),
+                    None => break,
+                }
+            }
+        }
+
+        vm.run(input, output)
+    }
+
+    /// Run the compiled program and the interpreter on copies of the VM with the same
+    /// input and check that they end in the same state with the same output.
+    pub fn verify(&self, vm: &IntCodeVM<W>, input: &[W]) -> bool {
+        let mut compiled = vm.clone();
+        let mut compiled_output = Vec::new();
+        let compiled_result = self.run(
+            &mut compiled,
+            &mut input.iter().cloned().collect::<VecDeque<_>>(),
+            &mut compiled_output,
+        );
+
+        let mut interpreted = vm.clone();
+        let mut interpreted_output = Vec::new();
+        let interpreted_result = interpreted.run(
+            &mut input.iter().cloned().collect::<VecDeque<_>>(),
+            &mut interpreted_output,
+        );
+
+        compiled == interpreted
+            && compiled_output == interpreted_output
+            && compiled_result == interpreted_result
+    }
+}
+
+// compile the instruction at the given address, returns None if it can't be
+// compiled and has to be interpreted
+fn compile<W: Word + 'static>(vm: &IntCodeVM<W>, address: usize) -> Option<Op<W>> {
+    let (instruction, operands) = match decode_memory(vm.memory(), address)? {
+        Line::Code {
+            instruction,
+            operands,
+            ..
+        } => (instruction, operands),
+        _ => return None,
+    };
+    let next = address + 1 + operands.len();
+
+    let args = operands
+        .iter()
+        .zip(address + 1..)
+        .map(|(operand, cell)| match operand.mode {
+            ParamMode::Immediate => Some(Arg::Immediate(cell)),
+            ParamMode::Positional => Some(Arg::Positional(cell)),
+            ParamMode::Relative => Some(Arg::Relative(cell)),
+            ParamMode::Invalid(_) => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let op: Op<W> = match instruction.code() {
+        code @ OpCode::Add
+        | code @ OpCode::Multiply
+        | code @ OpCode::LessThan
+        | code @ OpCode::Equals => {
+            let (lhs, rhs, dst) = (args[0], args[1], args[2]);
+
+            Box::new(move |vm, _, _, code_cells| {
+                let lhs = lhs.read(vm)?;
+                let rhs = rhs.read(vm)?;
+                let dst = dst.target(vm)?;
+
+                let result = match code {
+                    OpCode::Add => lhs.checked_add(&rhs)?,
+                    OpCode::Multiply => lhs.checked_mul(&rhs)?,
+                    OpCode::LessThan if lhs < rhs => W::one(),
+                    OpCode::Equals if lhs == rhs => W::one(),
+                    _ => W::zero(),
+                };
+
+                write(vm, code_cells, dst, result)?;
+
+                Some(next)
+            })
+        }
+        OpCode::Input => {
+            let dst = args[0];
+
+            Box::new(move |vm, input, _, code_cells| {
+                let dst = dst.target(vm)?;
+                // check the destination before consuming the input
+                if is_code(code_cells, dst) || vm.read_int(dst).is_err() {
+                    return None;
+                }
+                write(vm, code_cells, dst, input.read()?)?;
+
+                Some(next)
+            })
+        }
+        OpCode::Output => {
+            let value = args[0];
+
+            Box::new(move |vm, _, output, _| {
+                output.write(value.read(vm)?);
+
+                Some(next)
+            })
+        }
+        code @ OpCode::JumpIfTrue | code @ OpCode::JumpIfFalse => {
+            let (condition, destination) = (args[0], args[1]);
+
+            Box::new(move |vm, _, _, _| {
+                let condition = condition.read(vm)?;
+                let destination = destination.read(vm)?;
+
+                // the interpreter rejects negative destinations even if it doesn't jump
+                if destination < W::zero() {
+                    return None;
+                }
+
+                if (condition != W::zero()) == (code == OpCode::JumpIfTrue) {
+                    to_address(&destination)
+                } else {
+                    Some(next)
+                }
+            })
+        }
+        OpCode::AdjustRelativeBase => {
+            let offset = args[0];
+
+            Box::new(move |vm, _, _, _| {
+                let base = vm.relative_base().checked_add(&offset.read(vm)?)?;
+                vm.set_relative_base(base);
+
+                Some(next)
+            })
+        }
+        OpCode::Exit | OpCode::Invalid(_) => return None,
+    };
+
+    Some(op)
+}
+
+// writes to opcode cells are left to the interpreter, which has to be used from then on
+fn write<W: Word>(
+    vm: &mut IntCodeVM<W>,
+    code_cells: &[bool],
+    address: usize,
+    value: W,
+) -> Option<()> {
+    if is_code(code_cells, address) {
+        return None;
+    }
+
+    vm.set_int(address, value).ok()
+}
+
+// true if the given address holds the opcode of a compiled instruction
+fn is_code(code_cells: &[bool], address: usize) -> bool {
+    code_cells.get(address).copied().unwrap_or(false)
+}
+
+fn relative_address<W: Word>(vm: &IntCodeVM<W>, offset: &W) -> Option<usize> {
+    to_address(&vm.relative_base().checked_add(offset)?)
+}
+
+fn to_address<W: Word>(value: &W) -> Option<usize> {
+    value
+        .to_i64()
+        .and_then(|i| std::convert::TryFrom::try_from(i).ok())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::intcode::compiler::*;
+    use crate::intcode::Memory;
+
+    const COMPARE_TO_8: &str =
+        "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,\
+        1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99";
+
+    #[test]
+    fn test_compiler_equivalence() {
+        let programs = [
+            ("1,9,10,3,2,3,11,0,99,30,40,50", vec![]),
+            (COMPARE_TO_8, vec![7]),
+            (COMPARE_TO_8, vec![8]),
+            (COMPARE_TO_8, vec![9]),
+            // quine using the relative base
+            (
+                "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99",
+                vec![],
+            ),
+            // self-modifying, see the decode cache tests
+            (
+                "1001,21,3,21,4,21,1005,22,20,1101,1,1001,0,1101,0,1,22,1105,1,0,99,5,0",
+                vec![],
+            ),
+            // errors: missing input, overflow, negative address, out of bounds
+            ("3,0,99", vec![]),
+            ("1102,4611686018427387904,4,5,99,0", vec![]),
+            ("1,-1,0,0,99", vec![]),
+            ("1101,1,1,100,99", vec![]),
+            // negative destination of a jump that isn't taken
+            ("3,5,1106,1,-128", vec![0]),
+        ];
+
+        for (program, input) in programs.iter() {
+            let vm: IntCodeVM = program.parse().unwrap();
+            let compiled = CompiledProgram::new(&vm);
+
+            assert!(compiled.verify(&vm, input), "{}", program);
+        }
+    }
+
+    #[test]
+    fn test_compiler_run() {
+        let vm: IntCodeVM = COMPARE_TO_8.parse().unwrap();
+        let compiled = CompiledProgram::new(&vm);
+
+        for (input, expected) in &[(7, 999), (8, 1000), (9, 1001)] {
+            let mut vm = vm.clone();
+            let mut output = Vec::new();
+            compiled
+                .run(&mut vm, &mut VecDeque::from(vec![*input]), &mut output)
+                .unwrap();
+
+            assert_eq!(vec![*expected], output);
+            assert!(vm.is_halted());
+        }
+    }
+
+    #[test]
+    fn test_compiler_changed_code() {
+        let vm: IntCodeVM = "1,9,10,3,2,3,11,0,99,30,40,50".parse().unwrap();
+        let compiled = CompiledProgram::new(&vm);
+        // the exit instruction is left to the interpreter
+        assert_eq!(2, compiled.len());
+
+        // parameters are read when the instruction runs, so patching them keeps the
+        // compiled code
+        let mut patched = vm.clone();
+        patched.set_int(1, 10).unwrap();
+        assert!(compiled.matches(&patched));
+        assert!(compiled.verify(&patched, &[]));
+
+        compiled.run(&mut patched, &mut (), &mut ()).unwrap();
+        assert_eq!(Ok(4000), patched.read_int(0));
+
+        // changing an opcode invalidates it
+        let mut changed = vm.clone();
+        changed.set_int(0, 2).unwrap();
+        assert!(!compiled.matches(&changed));
+        assert!(compiled.verify(&changed, &[]));
+
+        let mut paged = IntCodeVM::with_memory(Memory::paged(vm.memory().to_vec()));
+        let compiled = CompiledProgram::new(&paged);
+        compiled.run(&mut paged, &mut (), &mut ()).unwrap();
+        assert_eq!(Ok(3500), paged.read_int(0));
+    }
+}
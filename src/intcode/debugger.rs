@@ -0,0 +1,648 @@
+use crate::intcode::disasm::{decode_memory, disassemble_memory};
+use crate::intcode::trace::History;
+use crate::intcode::{Instruction, IntCodeError, IntCodeVM, OpCode, Word};
+
+use std::collections::{BTreeSet, HashSet, VecDeque};
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "\
+step [n]              run n instructions (default 1)
+continue              run until a breakpoint, a watchpoint, input starvation or exit
+back [n]              undo n instructions (default 1)
+rcontinue             run backwards until a breakpoint, a write to a watched cell or the start
+rewind <n>            go back to the state after the first n executed instructions
+history [n]           print the last n executed instructions (default 10)
+break <addr|op>       stop before the instruction at addr or before every op, e.g. break OUT
+delete <addr|op>      remove a breakpoint
+watch <addr>          stop after the cell at addr changed
+unwatch <addr>        remove a watchpoint
+mem <addr> [n]        print n memory cells starting at addr (default 1)
+set <addr> <value>    store value at addr
+ptr [addr]            print or set the instruction pointer
+input <value>...      queue values for input instructions
+output                print all values the program emitted
+list [addr] [n]       disassemble n instructions starting at addr (default ptr, 10)
+info                  print pointer, relative base, breakpoints and watchpoints
+quit                  leave the debugger";
+
+/// Why execution stopped.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Stop<W> {
+    /// The requested number of instructions was executed.
+    Stepped,
+    Breakpoint(usize),
+    OpCode(OpCode),
+    Watchpoint {
+        address: usize,
+        old: W,
+        new: W,
+    },
+    /// An input instruction was reached but no input is queued.
+    NeedsInput,
+    Halted,
+    /// Stepping backwards reached the start of the history.
+    Start,
+    Error(IntCodeError),
+}
+
+/// Runs a VM instruction by instruction with breakpoints and watchpoints. Every
+/// executed instruction is recorded, so execution can be rewound and replayed.
+pub struct Debugger<W: Word> {
+    vm: IntCodeVM<W>,
+    history: History<W>,
+    breakpoints: BTreeSet<usize>,
+    opcode_breakpoints: HashSet<OpCode>,
+    watchpoints: BTreeSet<usize>,
+    input: VecDeque<W>,
+    output: Vec<W>,
+}
+
+impl<W: Word> Debugger<W> {
+    pub fn new(vm: IntCodeVM<W>) -> Self {
+        Self {
+            history: History::new(&vm),
+            vm,
+            breakpoints: BTreeSet::new(),
+            opcode_breakpoints: HashSet::new(),
+            watchpoints: BTreeSet::new(),
+            input: VecDeque::new(),
+            output: Vec::new(),
+        }
+    }
+
+    /// Keep only the last `limit` executed instructions, so long runs can't exhaust
+    /// memory. Stepping backwards stops at the oldest kept instruction.
+    pub fn with_history_limit(mut self, limit: usize) -> Self {
+        self.history = self.history.with_limit(limit);
+        self
+    }
+
+    pub fn vm(&self) -> &IntCodeVM<W> {
+        &self.vm
+    }
+
+    /// Changes made to the VM can't be undone, so the history is discarded.
+    pub fn vm_mut(&mut self) -> &mut IntCodeVM<W> {
+        self.history = History::new(&self.vm).with_limit(self.history.limit());
+        &mut self.vm
+    }
+
+    pub fn history(&self) -> &History<W> {
+        &self.history
+    }
+
+    pub fn add_breakpoint(&mut self, address: usize) {
+        self.breakpoints.insert(address);
+    }
+
+    pub fn add_opcode_breakpoint(&mut self, code: OpCode) {
+        self.opcode_breakpoints.insert(code);
+    }
+
+    pub fn add_watchpoint(&mut self, address: usize) {
+        self.watchpoints.insert(address);
+    }
+
+    pub fn push_input(&mut self, value: W) {
+        self.input.push_back(value);
+    }
+
+    pub fn output(&self) -> &[W] {
+        &self.output
+    }
+
+    /// Execute up to `count` instructions, stopping early on watchpoints, input
+    /// starvation, errors or exit.
+    pub fn step(&mut self, count: usize) -> Stop<W> {
+        for _ in 0..count {
+            if let Some(stop) = self.execute_one() {
+                return stop;
+            }
+        }
+
+        Stop::Stepped
+    }
+
+    /// Run until a breakpoint or any of the conditions of `step` is hit. The
+    /// instruction at the current pointer is always executed, so continuing from a
+    /// breakpoint doesn't stop at the same breakpoint again.
+    pub fn cont(&mut self) -> Stop<W> {
+        if let Some(stop) = self.execute_one() {
+            return stop;
+        }
+
+        loop {
+            let ptr = self.vm.ptr();
+
+            if self.breakpoints.contains(&ptr) {
+                return Stop::Breakpoint(ptr);
+            }
+
+            if let Ok(word) = self.vm.read_int(ptr) {
+                let code = Instruction::from_word(&word).code();
+
+                if self.opcode_breakpoints.contains(&code) {
+                    return Stop::OpCode(code);
+                }
+            }
+
+            if let Some(stop) = self.execute_one() {
+                return stop;
+            }
+        }
+    }
+
+    /// Undo up to `count` instructions. Consumed input is queued again and emitted
+    /// output is dropped, so running forward again replays the same execution.
+    pub fn step_back(&mut self, count: usize) -> Stop<W> {
+        for _ in 0..count {
+            if let Some(stop) = self.undo_one() {
+                return stop;
+            }
+        }
+
+        Stop::Stepped
+    }
+
+    /// Run backwards until the pointer is at a breakpoint, an instruction that
+    /// changed a watched cell is undone or the start of the history is reached.
+    pub fn reverse_cont(&mut self) -> Stop<W> {
+        loop {
+            if let Some(stop) = self.undo_one() {
+                return stop;
+            }
+
+            let ptr = self.vm.ptr();
+
+            if self.breakpoints.contains(&ptr) {
+                return Stop::Breakpoint(ptr);
+            }
+
+            if let Ok(word) = self.vm.read_int(ptr) {
+                let code = Instruction::from_word(&word).code();
+
+                if self.opcode_breakpoints.contains(&code) {
+                    return Stop::OpCode(code);
+                }
+            }
+        }
+    }
+
+    /// Go back to the state after the first `step` executed instructions.
+    pub fn rewind(&mut self, step: usize) -> Stop<W> {
+        self.step_back(self.history.executed().saturating_sub(step))
+    }
+
+    // undo a single instruction, returns the reason to stop if there is one
+    fn undo_one(&mut self) -> Option<Stop<W>> {
+        let step = match self.history.undo(&mut self.vm) {
+            Ok(Some(step)) => step,
+            Ok(None) => return Some(Stop::Start),
+            Err(e) => return Some(Stop::Error(e)),
+        };
+
+        if let Some(value) = step.input() {
+            self.input.push_front(value.clone());
+        }
+
+        if step.output.is_some() {
+            self.output.pop();
+        }
+
+        step.writes
+            .into_iter()
+            .find(|write| self.watchpoints.contains(&write.address) && write.old != write.new)
+            .map(|write| Stop::Watchpoint {
+                address: write.address,
+                old: write.old,
+                new: write.new,
+            })
+    }
+
+    // run a single instruction, returns the reason to stop if there is one
+    fn execute_one(&mut self) -> Option<Stop<W>> {
+        if self.vm.is_halted() {
+            return Some(Stop::Halted);
+        }
+
+        let watched = self
+            .watchpoints
+            .iter()
+            .map(|&address| (address, self.vm.read_int(address).ok()))
+            .collect::<Vec<_>>();
+
+        match self
+            .vm
+            .run_instruction_traced(&mut self.input, &mut self.output, &mut self.history)
+        {
+            Ok(true) => {}
+            Ok(false) => return Some(Stop::Halted),
+            Err(IntCodeError::InputExhausted { .. }) => return Some(Stop::NeedsInput),
+            Err(e) => return Some(Stop::Error(e)),
+        }
+
+        watched.into_iter().find_map(|(address, old)| {
+            let new = self.vm.read_int(address).ok();
+
+            match (old, new) {
+                (Some(old), Some(new)) if old != new => {
+                    Some(Stop::Watchpoint { address, old, new })
+                }
+                _ => None,
+            }
+        })
+    }
+
+    /// Read commands line by line and write the results, until the input ends or a
+    /// quit command is read.
+    pub fn repl<R: BufRead, O: Write>(&mut self, commands: R, mut out: O) -> io::Result<()> {
+        self.print_current(&mut out)?;
+
+        for line in commands.lines() {
+            if !self.execute(&line?, &mut out)? {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Execute a single debugger command. Returns false if the command was quit.
+    pub fn execute(&mut self, command: &str, out: &mut dyn Write) -> io::Result<bool> {
+        let args = command.split_whitespace().collect::<Vec<_>>();
+
+        let (name, args) = match args.split_first() {
+            Some((name, args)) => (*name, args),
+            None => return Ok(true),
+        };
+
+        match (name, args) {
+            ("step", args) | ("s", args) => match parse_or(args.first(), 1) {
+                Some(count) => {
+                    let stop = self.step(count);
+                    self.print_stop(&stop, out)?;
+                }
+                None => writeln!(out, "invalid step count")?,
+            },
+            ("continue", []) | ("c", []) => {
+                let stop = self.cont();
+                self.print_stop(&stop, out)?;
+            }
+            ("back", args) | ("bs", args) => match parse_or(args.first(), 1) {
+                Some(count) => {
+                    let stop = self.step_back(count);
+                    self.print_stop(&stop, out)?;
+                }
+                None => writeln!(out, "invalid step count")?,
+            },
+            ("rcontinue", []) | ("rc", []) => {
+                let stop = self.reverse_cont();
+                self.print_stop(&stop, out)?;
+            }
+            ("rewind", [step]) => match step.parse() {
+                Ok(step) if step >= self.history.forgotten() && step <= self.history.executed() => {
+                    let stop = self.rewind(step);
+                    self.print_stop(&stop, out)?;
+                }
+                _ => writeln!(out, "invalid step {}", step)?,
+            },
+            ("history", args) => match parse_or(args.first(), 10) {
+                Some(count) => {
+                    let steps = self.history.steps();
+
+                    for (index, step) in steps
+                        .iter()
+                        .enumerate()
+                        .skip(steps.len().saturating_sub(count))
+                    {
+                        writeln!(out, "#{}: {}", self.history.forgotten() + index + 1, step)?;
+                    }
+                }
+                None => writeln!(out, "usage: history [n]")?,
+            },
+            ("break", [target]) | ("b", [target]) => match parse_target(target) {
+                Some(Target::Address(address)) => {
+                    self.add_breakpoint(address);
+                    writeln!(out, "breakpoint at {:04}", address)?;
+                }
+                Some(Target::OpCode(code)) => {
+                    self.add_opcode_breakpoint(code);
+                    writeln!(out, "breakpoint on {}", code.mnemonic())?;
+                }
+                None => writeln!(out, "invalid breakpoint {}", target)?,
+            },
+            ("delete", [target]) | ("d", [target]) => {
+                let removed = match parse_target(target) {
+                    Some(Target::Address(address)) => self.breakpoints.remove(&address),
+                    Some(Target::OpCode(code)) => self.opcode_breakpoints.remove(&code),
+                    None => false,
+                };
+
+                if !removed {
+                    writeln!(out, "no breakpoint {}", target)?;
+                }
+            }
+            ("watch", [address]) | ("w", [address]) => match address.parse() {
+                Ok(address) => {
+                    self.add_watchpoint(address);
+                    writeln!(out, "watchpoint at {:04}", address)?;
+                }
+                Err(_) => writeln!(out, "invalid address {}", address)?,
+            },
+            ("unwatch", [address]) => match address.parse::<usize>() {
+                Ok(address) if self.watchpoints.remove(&address) => {}
+                _ => writeln!(out, "no watchpoint {}", address)?,
+            },
+            ("mem", [address, rest @ ..]) | ("x", [address, rest @ ..]) => {
+                match (address.parse::<usize>(), parse_or(rest.first(), 1)) {
+                    (Ok(address), Some(count)) => {
+                        for position in address..address.saturating_add(count) {
+                            match self.vm.read_int(position) {
+                                Ok(value) => writeln!(out, "{:04}: {}", position, value)?,
+                                Err(e) => {
+                                    writeln!(out, "{}", e)?;
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                    _ => writeln!(out, "usage: mem <addr> [n]")?,
+                }
+            }
+            ("set", [address, value]) => match (address.parse(), value.parse::<W>()) {
+                (Ok(address), Ok(value)) => {
+                    if let Err(e) = self.vm_mut().set_int(address, value) {
+                        writeln!(out, "{}", e)?;
+                    }
+                }
+                _ => writeln!(out, "usage: set <addr> <value>")?,
+            },
+            ("ptr", []) => writeln!(out, "{:04}", self.vm.ptr())?,
+            ("ptr", [address]) => match address.parse() {
+                Ok(address) => {
+                    self.vm_mut().jmp(address);
+                    self.print_current(out)?;
+                }
+                Err(_) => writeln!(out, "invalid address {}", address)?,
+            },
+            ("input", values) if !values.is_empty() => {
+                match values
+                    .iter()
+                    .map(|v| v.parse())
+                    .collect::<Result<Vec<W>, _>>()
+                {
+                    Ok(values) => self.input.extend(values),
+                    Err(_) => writeln!(out, "invalid input value")?,
+                }
+            }
+            ("output", []) => {
+                let output = self.output.iter().map(W::to_string).collect::<Vec<_>>();
+                writeln!(out, "{}", output.join(","))?;
+            }
+            ("list", args) | ("l", args) => {
+                let address = parse_or(args.first(), self.vm.ptr());
+                let count = parse_or(args.get(1), 10);
+
+                match (address, count) {
+                    (Some(address), Some(count)) => self.print_listing(address, count, out)?,
+                    _ => writeln!(out, "usage: list [addr] [n]")?,
+                }
+            }
+            ("info", []) => {
+                writeln!(out, "ptr: {:04}", self.vm.ptr())?;
+                writeln!(out, "relative base: {}", self.vm.relative_base())?;
+                writeln!(out, "halted: {}", self.vm.is_halted())?;
+                writeln!(out, "executed: {}", self.history.executed())?;
+                writeln!(out, "queued input: {}", self.input.len())?;
+
+                let breakpoints = self
+                    .breakpoints
+                    .iter()
+                    .map(|address| format!("{:04}", address))
+                    .chain(self.opcode_breakpoints.iter().map(|c| c.mnemonic().into()))
+                    .collect::<Vec<_>>();
+                writeln!(out, "breakpoints: {}", breakpoints.join(" "))?;
+
+                let watchpoints = self
+                    .watchpoints
+                    .iter()
+                    .map(|address| format!("{:04}", address))
+                    .collect::<Vec<_>>();
+                writeln!(out, "watchpoints: {}", watchpoints.join(" "))?;
+            }
+            ("help", []) | ("h", []) => writeln!(out, "{}", HELP)?,
+            ("quit", []) | ("q", []) => return Ok(false),
+            _ => writeln!(out, "unknown command {}, see help", command.trim())?,
+        }
+
+        Ok(true)
+    }
+
+    fn print_stop(&self, stop: &Stop<W>, out: &mut dyn Write) -> io::Result<()> {
+        match stop {
+            Stop::Stepped => {}
+            Stop::Breakpoint(address) => writeln!(out, "breakpoint at {:04}", address)?,
+            Stop::OpCode(code) => writeln!(out, "breakpoint on {}", code.mnemonic())?,
+            Stop::Watchpoint { address, old, new } => {
+                writeln!(out, "watchpoint at {:04}: {} -> {}", address, old, new)?
+            }
+            Stop::NeedsInput => writeln!(out, "waiting for input")?,
+            Stop::Halted => return writeln!(out, "halted"),
+            Stop::Start => writeln!(out, "reached the start of the history")?,
+            Stop::Error(e) => writeln!(out, "error: {}", e)?,
+        }
+
+        self.print_current(out)
+    }
+
+    fn print_current(&self, out: &mut dyn Write) -> io::Result<()> {
+        match decode_memory(self.vm.memory(), self.vm.ptr()) {
+            Some(line) => writeln!(out, "{}", line),
+            None => writeln!(out, "{:04}: <invalid instruction>", self.vm.ptr()),
+        }
+    }
+
+    fn print_listing(&self, address: usize, count: usize, out: &mut dyn Write) -> io::Result<()> {
+        write!(
+            out,
+            "{}",
+            disassemble_memory(self.vm.memory(), address, count)
+        )
+    }
+}
+
+enum Target {
+    Address(usize),
+    OpCode(OpCode),
+}
+
+fn parse_target(target: &str) -> Option<Target> {
+    match target.parse() {
+        Ok(address) => Some(Target::Address(address)),
+        Err(_) => OpCode::from_mnemonic(target).map(Target::OpCode),
+    }
+}
+
+// parse an optional numeric argument, falling back to the default if it's missing
+fn parse_or(arg: Option<&&str>, default: usize) -> Option<usize> {
+    match arg {
+        Some(arg) => arg.parse().ok(),
+        None => Some(default),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::intcode::debugger::*;
+
+    fn debugger() -> Debugger<i64> {
+        // count down from the input to zero, printing every value
+        Debugger::new("3,12,4,12,1001,12,-1,12,1005,12,2,99,0".parse().unwrap())
+    }
+
+    #[test]
+    fn test_debugger_breakpoints() {
+        let mut debugger = debugger();
+        debugger.push_input(2);
+        debugger.add_breakpoint(8);
+
+        assert_eq!(Stop::Breakpoint(8), debugger.cont());
+        assert_eq!(Stop::Breakpoint(8), debugger.cont());
+        assert_eq!(Stop::Halted, debugger.cont());
+        assert_eq!(&[2, 1], debugger.output());
+    }
+
+    #[test]
+    fn test_debugger_opcode_breakpoints_and_watchpoints() {
+        let mut debugger = debugger();
+        debugger.push_input(3);
+        debugger.add_opcode_breakpoint(OpCode::Output);
+        debugger.add_watchpoint(12);
+
+        assert_eq!(
+            Stop::Watchpoint {
+                address: 12,
+                old: 0,
+                new: 3
+            },
+            debugger.cont()
+        );
+        assert_eq!(
+            Stop::Watchpoint {
+                address: 12,
+                old: 3,
+                new: 2
+            },
+            debugger.cont()
+        );
+        assert_eq!(Stop::OpCode(OpCode::Output), debugger.cont());
+        assert_eq!(Stop::Stepped, debugger.step(1));
+        assert_eq!(&[3, 2], debugger.output());
+    }
+
+    #[test]
+    fn test_debugger_needs_input() {
+        let mut debugger = debugger();
+
+        assert_eq!(Stop::NeedsInput, debugger.step(1));
+        assert_eq!(0, debugger.vm().ptr());
+    }
+
+    #[test]
+    fn test_debugger_step_back() {
+        let mut debugger = debugger();
+        debugger.push_input(2);
+        let initial = debugger.vm().clone();
+
+        assert_eq!(Stop::Halted, debugger.cont());
+        assert_eq!(&[2, 1], debugger.output());
+        let executed = debugger.history().len();
+
+        assert_eq!(Stop::Stepped, debugger.step_back(2));
+        assert!(!debugger.vm().is_halted());
+        assert_eq!(8, debugger.vm().ptr());
+
+        assert_eq!(Stop::Start, debugger.step_back(executed));
+        assert_eq!(&initial, debugger.vm());
+        assert!(debugger.output().is_empty());
+
+        // the consumed input is queued again, so the run can be replayed
+        assert_eq!(Stop::Halted, debugger.cont());
+        assert_eq!(&[2, 1], debugger.output());
+
+        assert_eq!(Stop::Stepped, debugger.rewind(3));
+        assert_eq!(&[2], debugger.output());
+        assert_eq!(3, debugger.history().len());
+    }
+
+    #[test]
+    fn test_debugger_history_limit() {
+        let mut debugger = debugger().with_history_limit(4);
+        debugger.push_input(2);
+
+        assert_eq!(Stop::Halted, debugger.cont());
+        assert_eq!(4, debugger.history().len());
+        let executed = debugger.history().executed();
+
+        assert_eq!(Stop::Start, debugger.rewind(0));
+        assert_eq!(executed - 4, debugger.history().executed());
+        assert_eq!(2, debugger.vm_mut().ptr());
+        assert_eq!(4, debugger.history().limit());
+    }
+
+    #[test]
+    fn test_debugger_reverse_continue() {
+        let mut debugger = debugger();
+        debugger.push_input(3);
+        assert_eq!(Stop::Halted, debugger.cont());
+
+        // find the last instruction that changed the counter
+        debugger.add_watchpoint(12);
+        assert_eq!(
+            Stop::Watchpoint {
+                address: 12,
+                old: 1,
+                new: 0
+            },
+            debugger.reverse_cont()
+        );
+        assert_eq!(4, debugger.vm().ptr());
+
+        debugger.add_breakpoint(0);
+        debugger.watchpoints.clear();
+        assert_eq!(Stop::Breakpoint(0), debugger.reverse_cont());
+        assert_eq!(Stop::Start, debugger.reverse_cont());
+    }
+
+    #[test]
+    fn test_debugger_repl() {
+        let script = "\
+            break 8
+            input 1
+            continue
+            mem 12
+            set 12 5
+            ptr 11
+            step
+            output
+            foo
+            quit
+            step
+        ";
+        let mut out = Vec::new();
+        debugger().repl(script.as_bytes(), &mut out).unwrap();
+
+        assert_eq!(
+            "0000: IN -> [12]\n\
+             breakpoint at 0008\n\
+             breakpoint at 0008\n\
+             0008: JT [12], #2\n\
+             0012: 0\n\
+             0011: HLT\n\
+             halted\n\
+             1\n\
+             unknown command foo, see help\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+}
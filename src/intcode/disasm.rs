@@ -0,0 +1,426 @@
+use crate::intcode::{Instruction, Memory, OpCode, ParamMode, Word};
+
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+
+/// Maximum number of values printed in a single `.data` line.
+const DATA_PER_LINE: usize = 8;
+
+/// A decoded parameter of an instruction.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Operand<W> {
+    pub mode: ParamMode,
+    pub value: W,
+}
+
+impl<W: Word> Display for Operand<W> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.mode {
+            ParamMode::Positional => write!(f, "[{}]", self.value),
+            ParamMode::Immediate => write!(f, "#{}", self.value),
+            ParamMode::Relative if self.value < W::zero() => write!(f, "rel{}", self.value),
+            ParamMode::Relative => write!(f, "rel+{}", self.value),
+            ParamMode::Invalid(mode) => write!(f, "?{}:{}", mode, self.value),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Line<W> {
+    Code {
+        address: usize,
+        instruction: Instruction,
+        operands: Vec<Operand<W>>,
+    },
+    Data {
+        address: usize,
+        values: Vec<W>,
+    },
+    /// An instruction starting inside the parameters of the previous one, printed
+    /// as a comment so the listing still assembles.
+    Overlapping {
+        address: usize,
+        instruction: Instruction,
+        operands: Vec<Operand<W>>,
+    },
+}
+
+// print an instruction and its operands, without a line break
+fn write_code<W: Word>(
+    f: &mut Formatter<'_>,
+    address: usize,
+    instruction: &Instruction,
+    operands: &[Operand<W>],
+) -> std::fmt::Result {
+    write!(f, "{:04}: {}", address, instruction.code().mnemonic())?;
+
+    let (reads, write) = if instruction.code().writes_result() {
+        operands.split_at(operands.len() - 1)
+    } else {
+        operands.split_at(operands.len())
+    };
+
+    for (i, operand) in reads.iter().enumerate() {
+        write!(f, "{}{}", if i == 0 { " " } else { ", " }, operand)?;
+    }
+
+    if let Some(operand) = write.first() {
+        write!(f, " -> {}", operand)?;
+    }
+
+    Ok(())
+}
+
+impl<W: Word> Display for Line<W> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Line::Code {
+                address,
+                instruction,
+                operands,
+            } => write_code(f, *address, instruction, operands),
+            Line::Data { address, values } => {
+                let values = values
+                    .iter()
+                    .map(|value| value.to_string())
+                    .collect::<Vec<_>>();
+
+                write!(f, "{:04}: .data {}", address, values.join(", "))
+            }
+            Line::Overlapping {
+                address,
+                instruction,
+                operands,
+            } => {
+                write!(f, "; ")?;
+                write_code(f, *address, instruction, operands)?;
+                write!(f, " (overlaps the previous instruction)")
+            }
+        }
+    }
+}
+
+/// An annotated listing of a program, one instruction or run of data per line.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Listing<W>(pub Vec<Line<W>>);
+
+impl<W: Word> Display for Listing<W> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for line in &self.0 {
+            writeln!(f, "{}", line)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Disassemble a memory image. Only instructions reachable from address zero are
+/// listed as code, everything else is listed as data.
+pub fn disassemble<W: Word>(program: &[W]) -> Listing<W> {
+    listing(program, &reachable(program))
+}
+
+/// Disassemble a memory image front to back, treating every cell that decodes to a
+/// valid instruction as code. Useful if jump targets are computed at runtime.
+pub fn disassemble_linear<W: Word>(program: &[W]) -> Listing<W> {
+    let mut starts = BTreeSet::new();
+    let mut address = 0;
+
+    while address < program.len() {
+        match decode(program, address) {
+            Some((instruction, _)) => {
+                starts.insert(address);
+                address += 1 + instruction.code().parameter_count();
+            }
+            None => address += 1,
+        }
+    }
+
+    listing(program, &starts)
+}
+
+/// Decode the instruction at the given address. Returns None if the cell isn't a
+/// valid instruction or its parameters run past the end of the program.
+pub fn decode<W: Word>(program: &[W], address: usize) -> Option<(Instruction, Vec<Operand<W>>)> {
+    let instruction = Instruction::from_word(program.get(address)?);
+    let count = instruction.code().parameter_count();
+
+    if let OpCode::Invalid(_) = instruction.code() {
+        return None;
+    }
+
+    let operands = (0..count)
+        .map(|i| {
+            let mode = instruction.get_param_mode(i);
+            let value = program.get(address + 1 + i)?.clone();
+
+            match mode {
+                ParamMode::Invalid(_) => None,
+                ParamMode::Immediate if instruction.code().writes_result() && i == count - 1 => {
+                    None
+                }
+                _ => Some(Operand { mode, value }),
+            }
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    Some((instruction, operands))
+}
+
+/// Decode the instruction at the given address of the VM's memory as listing line.
+pub fn decode_memory<W: Word>(memory: &Memory<W>, address: usize) -> Option<Line<W>> {
+    let (instruction, operands) = decode_window(memory, address)?;
+
+    Some(Line::Code {
+        address,
+        instruction,
+        operands,
+    })
+}
+
+// decode an instruction from a copy of the few cells it can span
+fn decode_window<W: Word>(
+    memory: &Memory<W>,
+    address: usize,
+) -> Option<(Instruction, Vec<Operand<W>>)> {
+    let window = (address..address.saturating_add(4))
+        .map_while(|position| memory.get(position))
+        .collect::<Vec<_>>();
+
+    decode(&window, 0)
+}
+
+/// Disassemble `count` lines of the VM's memory front to back, starting at the given
+/// address. Cells that don't decode to a valid instruction are listed as data.
+pub fn disassemble_memory<W: Word>(memory: &Memory<W>, address: usize, count: usize) -> Listing<W> {
+    let mut lines = Vec::new();
+    let mut address = address;
+
+    while lines.len() < count {
+        match decode_memory(memory, address) {
+            Some(Line::Code {
+                address: start,
+                instruction,
+                operands,
+            }) => {
+                address += 1 + operands.len();
+                lines.push(Line::Code {
+                    address: start,
+                    instruction,
+                    operands,
+                });
+            }
+            _ => match memory.get(address) {
+                Some(value) => {
+                    lines.push(Line::Data {
+                        address,
+                        values: vec![value],
+                    });
+                    address += 1;
+                }
+                None => break,
+            },
+        }
+    }
+
+    Listing(lines)
+}
+
+/// Find the start addresses of all instructions reachable from address zero. Jumps
+/// are only followed if their destination is an immediate value, jumps whose
+/// condition is an immediate value are resolved statically.
+pub fn reachable<W: Word>(program: &[W]) -> BTreeSet<usize> {
+    reachable_by(|address| decode(program, address))
+}
+
+/// Like `reachable`, but decodes the VM's memory in place instead of a copy of it.
+pub fn reachable_memory<W: Word>(memory: &Memory<W>) -> BTreeSet<usize> {
+    reachable_by(|address| decode_window(memory, address))
+}
+
+fn reachable_by<W: Word>(
+    decode: impl Fn(usize) -> Option<(Instruction, Vec<Operand<W>>)>,
+) -> BTreeSet<usize> {
+    let mut starts = BTreeSet::new();
+    let mut pending = vec![0];
+
+    while let Some(address) = pending.pop() {
+        if starts.contains(&address) {
+            continue;
+        }
+
+        let (instruction, operands) = match decode(address) {
+            Some(decoded) => decoded,
+            None => continue,
+        };
+
+        starts.insert(address);
+        pending.extend(successors(address, &instruction, &operands));
+    }
+
+    starts
+}
+
+/// The addresses execution may continue at after the given instruction, as far as
+/// they are known statically.
+pub fn successors<W: Word>(
+    address: usize,
+    instruction: &Instruction,
+    operands: &[Operand<W>],
+) -> Vec<usize> {
+    let next = address + 1 + operands.len();
+
+    let (jumps_if_true, condition, destination) = match instruction.code() {
+        OpCode::Exit => return Vec::new(),
+        OpCode::JumpIfTrue => (true, &operands[0], &operands[1]),
+        OpCode::JumpIfFalse => (false, &operands[0], &operands[1]),
+        _ => return vec![next],
+    };
+
+    let destination = match destination.mode {
+        ParamMode::Immediate => destination
+            .value
+            .to_i64()
+            .and_then(|d| std::convert::TryFrom::try_from(d).ok()),
+        _ => None,
+    };
+
+    let taken = match condition.mode {
+        ParamMode::Immediate => Some((condition.value != W::zero()) == jumps_if_true),
+        _ => None,
+    };
+
+    match (taken, destination) {
+        (Some(true), Some(destination)) => vec![destination],
+        (Some(true), None) => Vec::new(),
+        (Some(false), _) => vec![next],
+        (None, Some(destination)) => vec![next, destination],
+        (None, None) => vec![next],
+    }
+}
+
+// build the listing from the given instruction start addresses
+fn listing<W: Word>(program: &[W], starts: &BTreeSet<usize>) -> Listing<W> {
+    let mut lines = Vec::new();
+    let mut address = 0;
+
+    while address < program.len() {
+        if starts.contains(&address) {
+            let (instruction, operands) = decode(program, address).unwrap();
+            let next = address + 1 + operands.len();
+
+            lines.push(Line::Code {
+                address,
+                instruction,
+                operands,
+            });
+
+            // jumps into the parameters of this instruction, e.g. into an immediate
+            for start in starts.range(address + 1..next) {
+                let (instruction, operands) = decode(program, *start).unwrap();
+
+                lines.push(Line::Overlapping {
+                    address: *start,
+                    instruction,
+                    operands,
+                });
+            }
+
+            address = next;
+        } else {
+            let end = (address + 1..program.len())
+                .find(|a| starts.contains(a))
+                .unwrap_or(program.len())
+                .min(address + DATA_PER_LINE);
+
+            lines.push(Line::Data {
+                address,
+                values: program[address..end].to_vec(),
+            });
+            address = end;
+        }
+    }
+
+    Listing(lines)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::intcode::asm::assemble;
+    use crate::intcode::disasm::*;
+
+    #[test]
+    fn test_operand_display() {
+        let operand = |mode, value| Operand { mode, value }.to_string();
+
+        assert_eq!("[225]", operand(ParamMode::Positional, 225));
+        assert_eq!("#-6", operand(ParamMode::Immediate, -6));
+        assert_eq!("rel+3", operand(ParamMode::Relative, 3));
+        assert_eq!("rel-3", operand(ParamMode::Relative, -3));
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let program = vec![1101, 5, 6, 12, 1105, 1, 9, 1, 1, 4, 12, 99, 0, 7, 7];
+
+        assert_eq!(
+            "0000: ADD #5, #6 -> [12]\n\
+             0004: JT #1, #9\n\
+             0007: .data 1, 1\n\
+             0009: OUT [12]\n\
+             0011: HLT\n\
+             0012: .data 0, 7, 7\n",
+            disassemble(&program).to_string()
+        );
+    }
+
+    #[test]
+    fn test_disassemble_linear() {
+        let program = vec![1005, 12, 9, 1, 1, 1, 0, 4, 12, 99];
+
+        assert_eq!(
+            "0000: JT [12], #9\n\
+             0003: ADD [1], [1] -> [0]\n\
+             0007: OUT [12]\n\
+             0009: HLT\n",
+            disassemble_linear(&program).to_string()
+        );
+    }
+
+    #[test]
+    fn test_reachable_static_jumps() {
+        // JT #1, #6 always jumps, so the add in between is never executed
+        let program = vec![1105, 1, 6, 1, 0, 0, 99];
+
+        assert_eq!(
+            vec![0, 6],
+            reachable(&program).into_iter().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            reachable(&program),
+            reachable_memory(&Memory::paged(program))
+        );
+    }
+
+    #[test]
+    fn test_disassemble_overlapping() {
+        // JT #1, #2 jumps into its own destination, which is a MUL
+        let program = vec![1105, 1, 2, 1, 1, 99, 99];
+
+        assert_eq!(
+            vec![0, 2, 6],
+            reachable(&program).into_iter().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            "0000: JT #1, #2\n\
+             ; 0002: MUL [1], [1] -> [99] (overlaps the previous instruction)\n\
+             0003: .data 1, 1, 99\n\
+             0006: HLT\n",
+            disassemble(&program).to_string()
+        );
+        assert_eq!(
+            program,
+            assemble(&disassemble(&program).to_string()).unwrap()
+        );
+    }
+}
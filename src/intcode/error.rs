@@ -0,0 +1,164 @@
+use crate::intcode::Instruction;
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+/// Everything that can go wrong while running a program. Every error carries the
+/// instruction pointer at the time of the fault and the instruction found there.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum IntCodeError {
+    /// The instruction has an unknown opcode.
+    InvalidOpCode {
+        ptr: usize,
+        instruction: Instruction,
+    },
+    /// A parameter has a mode other than positional, immediate or relative.
+    InvalidParameterMode {
+        ptr: usize,
+        instruction: Instruction,
+        parameter: usize,
+    },
+    /// The parameter an instruction writes its result to is in immediate mode.
+    ImmediateWrite {
+        ptr: usize,
+        instruction: Instruction,
+        parameter: usize,
+    },
+    /// An address at or beyond the memory limit was accessed.
+    OutOfBounds {
+        ptr: usize,
+        instruction: Instruction,
+        address: usize,
+    },
+    /// A parameter or jump destination resolved to a negative address.
+    NegativeAddress {
+        ptr: usize,
+        instruction: Instruction,
+        address: String,
+    },
+    /// An input instruction was reached but no input is available.
+    InputExhausted {
+        ptr: usize,
+        instruction: Instruction,
+    },
+    /// The result of an addition, multiplication or relative base adjustment doesn't
+    /// fit into a word.
+    ArithmeticOverflow {
+        ptr: usize,
+        instruction: Instruction,
+    },
+    /// The program didn't halt within the allowed number of instructions.
+    StepLimitExceeded {
+        ptr: usize,
+        instruction: Instruction,
+        limit: u64,
+    },
+    /// The program didn't halt within the allowed time.
+    TimeLimitExceeded {
+        ptr: usize,
+        instruction: Instruction,
+        limit: Duration,
+    },
+    /// The program allocated more than the allowed number of memory cells.
+    MemoryLimitExceeded {
+        ptr: usize,
+        instruction: Instruction,
+        limit: usize,
+    },
+    /// The VM reached a state it was in before without any input or output in
+    /// between, so it will never halt.
+    InfiniteLoop {
+        ptr: usize,
+        instruction: Instruction,
+    },
+}
+
+impl IntCodeError {
+    /// The instruction pointer at the time of the fault.
+    pub fn ptr(&self) -> usize {
+        match self {
+            IntCodeError::InvalidOpCode { ptr, .. }
+            | IntCodeError::InvalidParameterMode { ptr, .. }
+            | IntCodeError::ImmediateWrite { ptr, .. }
+            | IntCodeError::OutOfBounds { ptr, .. }
+            | IntCodeError::NegativeAddress { ptr, .. }
+            | IntCodeError::InputExhausted { ptr, .. }
+            | IntCodeError::ArithmeticOverflow { ptr, .. }
+            | IntCodeError::StepLimitExceeded { ptr, .. }
+            | IntCodeError::TimeLimitExceeded { ptr, .. }
+            | IntCodeError::MemoryLimitExceeded { ptr, .. }
+            | IntCodeError::InfiniteLoop { ptr, .. } => *ptr,
+        }
+    }
+
+    /// The instruction at the instruction pointer at the time of the fault.
+    pub fn instruction(&self) -> &Instruction {
+        match self {
+            IntCodeError::InvalidOpCode { instruction, .. }
+            | IntCodeError::InvalidParameterMode { instruction, .. }
+            | IntCodeError::ImmediateWrite { instruction, .. }
+            | IntCodeError::OutOfBounds { instruction, .. }
+            | IntCodeError::NegativeAddress { instruction, .. }
+            | IntCodeError::InputExhausted { instruction, .. }
+            | IntCodeError::ArithmeticOverflow { instruction, .. }
+            | IntCodeError::StepLimitExceeded { instruction, .. }
+            | IntCodeError::TimeLimitExceeded { instruction, .. }
+            | IntCodeError::MemoryLimitExceeded { instruction, .. }
+            | IntCodeError::InfiniteLoop { instruction, .. } => instruction,
+        }
+    }
+}
+
+impl Display for IntCodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IntCodeError::InvalidOpCode { instruction, .. } => {
+                write!(f, "invalid opcode {}", u32::from(instruction.code()))?
+            }
+            IntCodeError::InvalidParameterMode {
+                instruction,
+                parameter,
+                ..
+            } => write!(
+                f,
+                "invalid mode {} of parameter {}",
+                u32::from(instruction.get_param_mode(*parameter)),
+                parameter + 1
+            )?,
+            IntCodeError::ImmediateWrite { parameter, .. } => write!(
+                f,
+                "parameter {} is written to but in immediate mode",
+                parameter + 1
+            )?,
+            IntCodeError::OutOfBounds { address, .. } => {
+                write!(f, "address {} exceeds the memory limit", address)?
+            }
+            IntCodeError::NegativeAddress { address, .. } => {
+                write!(f, "negative address {}", address)?
+            }
+            IntCodeError::InputExhausted { .. } => write!(f, "no input available")?,
+            IntCodeError::ArithmeticOverflow { .. } => write!(f, "arithmetic overflow")?,
+            IntCodeError::StepLimitExceeded { limit, .. } => {
+                write!(f, "program didn't halt within {} instructions", limit)?
+            }
+            IntCodeError::TimeLimitExceeded { limit, .. } => {
+                write!(f, "program didn't halt within {:?}", limit)?
+            }
+            IntCodeError::MemoryLimitExceeded { limit, .. } => {
+                write!(f, "program allocated more than {} memory cells", limit)?
+            }
+            IntCodeError::InfiniteLoop { .. } => write!(f, "program is stuck in an infinite loop")?,
+        };
+
+        write!(
+            f,
+            " in instruction {} ({}) at position {}",
+            self.instruction().to_int(),
+            self.instruction().code().mnemonic(),
+            self.ptr()
+        )
+    }
+}
+
+impl Error for IntCodeError {}
@@ -0,0 +1,330 @@
+//! Helpers for fuzzing the VM, shared by the fuzz targets in `fuzz/` and the
+//! property tests.
+//!
+//! `check` runs a program in every configuration of the VM and compares the
+//! results: with and without the decode cache, on dense and paged memory, compiled
+//! and executed symbolically without symbols. Programs of the day 2 instruction set
+//! are also run by `reference_run`, which shares no code with the VM. Any panic or
+//! disagreement is a bug.
+//!
+//! Only built for tests and with the `fuzzing` feature.
+
+use crate::intcode::cfg::ControlFlowGraph;
+use crate::intcode::compiler::CompiledProgram;
+use crate::intcode::disasm::disassemble;
+use crate::intcode::symbolic::{Expr, SymbolicError, SymbolicVM};
+use crate::intcode::{Budget, IntCodeError, IntCodeVM, Memory, OpCode};
+
+use std::collections::{BTreeMap, VecDeque};
+use std::convert::TryFrom;
+
+/// Memory limit of the VMs run by `check`, so runaway writes stay cheap.
+pub const MAX_MEMORY: usize = 1 << 12;
+
+/// Number of instructions `check` executes before giving up on a program.
+pub const MAX_STEPS: u64 = 10_000;
+
+const OPCODES: [OpCode; 10] = [
+    OpCode::Add,
+    OpCode::Multiply,
+    OpCode::Input,
+    OpCode::Output,
+    OpCode::JumpIfTrue,
+    OpCode::JumpIfFalse,
+    OpCode::LessThan,
+    OpCode::Equals,
+    OpCode::AdjustRelativeBase,
+    OpCode::Exit,
+];
+
+/// Turn arbitrary bytes into a program of valid instructions. Every instruction
+/// takes a byte for its opcode, one for its parameter modes and one per parameter.
+/// Results are never written to immediate parameters and positional parameters
+/// point to the first 64 cells, other parameters are small signed values.
+pub fn structured_program(data: &[u8]) -> Vec<i64> {
+    let mut bytes = data.iter().copied();
+    let mut program = Vec::new();
+
+    while let Some(byte) = bytes.next() {
+        let code = OPCODES[usize::from(byte) % OPCODES.len()];
+        let modes = bytes.next().unwrap_or(0);
+        let count = code.parameter_count();
+        let mut instruction = i64::from(u32::from(code));
+        let mut parameters = Vec::new();
+
+        for i in 0..count {
+            let mut mode = ((modes >> (2 * i)) & 0b11) % 3;
+
+            if mode == 1 && code.writes_result() && i == count - 1 {
+                mode = 0;
+            }
+
+            let byte = bytes.next().unwrap_or(0);
+            instruction += i64::from(mode) * 10i64.pow(i as u32 + 2);
+            parameters.push(match mode {
+                0 => i64::from(byte % 64),
+                _ => i64::from(byte as i8),
+            });
+        }
+
+        program.push(instruction);
+        program.extend(parameters);
+    }
+
+    program
+}
+
+/// Run the program with the given input in every configuration and panic if any of
+/// them disagrees with the interpreter. Errors are fine, panics are not.
+pub fn check(program: &[i64], input: &[i64]) {
+    disassemble(program);
+    ControlFlowGraph::new(program);
+
+    let budget = Budget::default().with_max_steps(MAX_STEPS);
+    let run = |mut vm: IntCodeVM| {
+        let mut output = Vec::new();
+        let result = vm.run_with_budget(
+            &mut input.iter().copied().collect::<VecDeque<_>>(),
+            &mut output,
+            &budget,
+        );
+
+        (result, output, state(&vm))
+    };
+
+    let dense = IntCodeVM::with_memory(Memory::dense(program.to_vec()).with_max_size(MAX_MEMORY));
+    let paged = IntCodeVM::with_memory(Memory::paged(program.to_vec()).with_max_size(MAX_MEMORY));
+    let mut uncached = dense.clone();
+    uncached.set_decode_cache(false);
+
+    let reference = run(dense.clone());
+    assert_eq!(
+        reference,
+        run(uncached),
+        "cached and uncached decoding disagree"
+    );
+    assert_eq!(reference, run(paged), "dense and paged memory disagree");
+
+    let (result, output, final_state) = reference;
+
+    if let Some(memory) = reference_run(program) {
+        let memory = (0..)
+            .zip(memory)
+            .filter(|(_, value)| *value != 0)
+            .collect::<BTreeMap<_, _>>();
+
+        assert_eq!(
+            (Ok(()), true, &memory),
+            (result.clone(), final_state.halted, &final_state.memory),
+            "reference interpreter and VM disagree"
+        );
+    }
+
+    if let Err(IntCodeError::StepLimitExceeded { .. }) = result {
+        return;
+    }
+
+    assert!(
+        CompiledProgram::new(&dense).verify(&dense, input),
+        "compiled code and interpreter disagree"
+    );
+
+    let mut symbolic = SymbolicVM::new(&dense).with_max_steps(MAX_STEPS);
+
+    for value in input {
+        symbolic.push_input(Expr::Const(*value));
+    }
+
+    match (symbolic.run(), &result) {
+        (Ok(()), Ok(())) => {
+            let symbolic_output = symbolic
+                .output()
+                .iter()
+                .map(|value| value.as_const().copied())
+                .collect::<Option<Vec<_>>>();
+            let symbolic_memory = symbolic
+                .cells()
+                .filter(|(_, cell)| **cell != Expr::Const(0))
+                .map(|(address, cell)| Some((address, *cell.as_const()?)))
+                .collect::<Option<BTreeMap<_, _>>>();
+
+            assert_eq!(Some(&output), symbolic_output.as_ref());
+            assert_eq!(Some(&final_state.memory), symbolic_memory.as_ref());
+        }
+        (Err(SymbolicError::IntCode(_)), Err(_)) => {}
+        (symbolic, result) => panic!(
+            "symbolic execution and interpreter disagree: {:?}, {:?}",
+            symbolic, result
+        ),
+    }
+}
+
+/// Run a program of add, multiply and exit instructions with positional parameters,
+/// the instruction set of day 2, and return the final memory. Written independently
+/// of the VM to serve as its oracle. Returns None for programs outside that subset,
+/// including any access beyond the end of the program or an overflow.
+pub fn reference_run(program: &[i64]) -> Option<Vec<i64>> {
+    let mut memory = program.to_vec();
+    let mut ptr = 0;
+
+    loop {
+        let code = *memory.get(ptr)?;
+
+        if code == 99 {
+            return Some(memory);
+        }
+
+        let lhs = memory[reference_address(&memory, ptr + 1)?];
+        let rhs = memory[reference_address(&memory, ptr + 2)?];
+        let dst = reference_address(&memory, ptr + 3)?;
+
+        memory[dst] = match code {
+            1 => lhs.checked_add(rhs)?,
+            2 => lhs.checked_mul(rhs)?,
+            _ => return None,
+        };
+        ptr += 4;
+    }
+}
+
+// the address stored at the given position, if it is within the program
+fn reference_address(memory: &[i64], position: usize) -> Option<usize> {
+    let address = usize::try_from(*memory.get(position)?).ok()?;
+
+    if address < memory.len() {
+        Some(address)
+    } else {
+        None
+    }
+}
+
+/// The observable state of a VM, memory as its non-zero cells by address.
+#[derive(Debug, PartialEq, Eq)]
+struct State {
+    ptr: usize,
+    relative_base: i64,
+    halted: bool,
+    memory: BTreeMap<usize, i64>,
+}
+
+fn state(vm: &IntCodeVM) -> State {
+    let memory = vm
+        .memory()
+        .cells()
+        .filter(|(_, value)| **value != 0)
+        .map(|(address, value)| (address, *value))
+        .collect();
+
+    State {
+        ptr: vm.ptr(),
+        relative_base: *vm.relative_base(),
+        halted: vm.is_halted(),
+        memory,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::intcode::fuzz::*;
+
+    use proptest::prelude::*;
+
+    /// Programs of arbitrary cells, biased towards small values and valid opcodes.
+    fn random_program() -> impl Strategy<Value = Vec<i64>> {
+        prop::collection::vec(
+            prop_oneof![
+                -10i64..100,
+                prop::sample::select(vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 99, 1101, 1002, 204, 109]),
+                any::<i64>(),
+            ],
+            0..64,
+        )
+    }
+
+    /// Programs made of valid instructions, see `structured_program`.
+    fn valid_program() -> impl Strategy<Value = Vec<i64>> {
+        prop::collection::vec(any::<u8>(), 0..128).prop_map(|data| structured_program(&data))
+    }
+
+    /// Programs of add, multiply and exit instructions followed by data, see
+    /// `reference_run`. Parameters read from anywhere in the program, results are
+    /// only written to the data.
+    fn day2_program() -> impl Strategy<Value = Vec<i64>> {
+        (1usize..16, 1usize..8).prop_flat_map(|(instructions, data)| {
+            let code = 4 * instructions + 1;
+            let len = (code + data) as i64;
+            let instruction = (
+                prop::sample::select(vec![1, 2]),
+                0..len,
+                0..len,
+                code as i64..len,
+            );
+
+            (
+                prop::collection::vec(instruction, instructions),
+                prop::collection::vec(-10i64..10, data),
+            )
+                .prop_map(|(instructions, data)| {
+                    let mut program = Vec::new();
+
+                    for (code, lhs, rhs, dst) in instructions {
+                        program.extend(&[code, lhs, rhs, dst]);
+                    }
+
+                    program.push(99);
+                    program.extend(data);
+                    program
+                })
+        })
+    }
+
+    fn input() -> impl Strategy<Value = Vec<i64>> {
+        prop::collection::vec(-5i64..10, 0..4)
+    }
+
+    #[test]
+    fn test_reference_run() {
+        assert_eq!(
+            Some(vec![3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50]),
+            reference_run(&[1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50])
+        );
+        assert_eq!(
+            Some(vec![30, 1, 1, 4, 2, 5, 6, 0, 99]),
+            reference_run(&[1, 1, 1, 4, 99, 5, 6, 0, 99])
+        );
+        // outside of the subset
+        assert_eq!(None, reference_run(&[1101, 1, 1, 0, 99]));
+        assert_eq!(None, reference_run(&[1, 0, 0, 100, 99]));
+        assert_eq!(None, reference_run(&[1, 0, 0, 0]));
+    }
+
+    #[test]
+    fn test_structured_program() {
+        // ADD with modes 1, 2 and 1, the immediate write turns positional
+        let program = structured_program(&[0, 0b01_10_01, 1, 2, 3, 9]);
+
+        assert_eq!(vec![2101, 1, 2, 3, 99], program);
+        assert_eq!(
+            "0000: ADD #1, rel+2 -> [3]\n0004: HLT\n",
+            disassemble(&program).to_string()
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_fuzz_random_programs(program in random_program(), input in input()) {
+            check(&program, &input);
+        }
+
+        #[test]
+        fn test_fuzz_valid_programs(program in valid_program(), input in input()) {
+            check(&program, &input);
+        }
+
+        #[test]
+        fn test_fuzz_day2_programs(program in day2_program()) {
+            prop_assume!(reference_run(&program).is_some());
+            check(&program, &[]);
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use crate::intcode::Word;
+
+/// A decoded instruction. Decoding is pure arithmetic, so instructions are cheap to
+/// create and copy.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Instruction {
+    code: OpCode,
+    parameter_modes: [ParamMode; 3],
+    // the encoded instruction, kept to report it exactly as found in memory
+    raw: u32,
+}
+
+impl Instruction {
+    pub fn from_int(i: u32) -> Self {
+        Instruction {
+            code: OpCode::from(i % 100),
+            parameter_modes: [
+                ParamMode::from(i / 100 % 10),
+                ParamMode::from(i / 1_000 % 10),
+                ParamMode::from(i / 10_000 % 10),
+            ],
+            raw: i,
+        }
+    }
+
+    /// Decode a memory cell. Cells that don't fit into u32 decode as invalid instruction.
+    pub fn from_word<W: Word>(word: &W) -> Self {
+        Self::from_int(
+            word.to_i64()
+                .and_then(|i| std::convert::TryFrom::try_from(i).ok())
+                .unwrap_or(u32::MAX),
+        )
+    }
+
+    /// Encode the instruction as the integer it was decoded from.
+    pub fn to_int(&self) -> u32 {
+        self.raw
+    }
+
+    pub fn code(&self) -> OpCode {
+        self.code
+    }
+
+    /// Get the mode for the given parameter. Defaults to zero
+    pub fn get_param_mode(&self, param: usize) -> ParamMode {
+        *self
+            .parameter_modes
+            .get(param)
+            .unwrap_or(&ParamMode::Positional)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ParamMode {
+    Positional,
+    Immediate,
+    Relative,
+    Invalid(u32),
+}
+
+impl From<u32> for ParamMode {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => ParamMode::Positional,
+            1 => ParamMode::Immediate,
+            2 => ParamMode::Relative,
+            n => ParamMode::Invalid(n),
+        }
+    }
+}
+
+impl From<ParamMode> for u32 {
+    fn from(mode: ParamMode) -> Self {
+        match mode {
+            ParamMode::Positional => 0,
+            ParamMode::Immediate => 1,
+            ParamMode::Relative => 2,
+            ParamMode::Invalid(n) => n,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum OpCode {
+    Add,
+    Multiply,
+    Input,
+    Output,
+    JumpIfTrue,
+    JumpIfFalse,
+    LessThan,
+    Equals,
+    AdjustRelativeBase,
+    Exit,
+    Invalid(u32),
+}
+
+impl OpCode {
+    /// The assembly mnemonic of the instruction.
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            OpCode::Add => "ADD",
+            OpCode::Multiply => "MUL",
+            OpCode::Input => "IN",
+            OpCode::Output => "OUT",
+            OpCode::JumpIfTrue => "JT",
+            OpCode::JumpIfFalse => "JF",
+            OpCode::LessThan => "LT",
+            OpCode::Equals => "EQ",
+            OpCode::AdjustRelativeBase => "ARB",
+            OpCode::Exit => "HLT",
+            OpCode::Invalid(_) => "???",
+        }
+    }
+
+    /// Look up an instruction by its mnemonic, ignoring case.
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Self> {
+        (1..=9)
+            .chain(std::iter::once(99))
+            .map(OpCode::from)
+            .find(|code| code.mnemonic().eq_ignore_ascii_case(mnemonic))
+    }
+
+    /// Number of parameters following the instruction.
+    pub fn parameter_count(self) -> usize {
+        match self {
+            OpCode::Add | OpCode::Multiply | OpCode::LessThan | OpCode::Equals => 3,
+            OpCode::JumpIfTrue | OpCode::JumpIfFalse => 2,
+            OpCode::Input | OpCode::Output | OpCode::AdjustRelativeBase => 1,
+            OpCode::Exit | OpCode::Invalid(_) => 0,
+        }
+    }
+
+    /// Returns true if the last parameter is the address the result is written to.
+    pub fn writes_result(self) -> bool {
+        matches!(
+            self,
+            OpCode::Add | OpCode::Multiply | OpCode::LessThan | OpCode::Equals | OpCode::Input
+        )
+    }
+}
+
+impl From<u32> for OpCode {
+    fn from(code: u32) -> Self {
+        match code {
+            1 => OpCode::Add,
+            2 => OpCode::Multiply,
+            3 => OpCode::Input,
+            4 => OpCode::Output,
+            5 => OpCode::JumpIfTrue,
+            6 => OpCode::JumpIfFalse,
+            7 => OpCode::LessThan,
+            8 => OpCode::Equals,
+            9 => OpCode::AdjustRelativeBase,
+            99 => OpCode::Exit,
+            n => OpCode::Invalid(n),
+        }
+    }
+}
+
+impl From<OpCode> for u32 {
+    fn from(code: OpCode) -> Self {
+        match code {
+            OpCode::Add => 1,
+            OpCode::Multiply => 2,
+            OpCode::Input => 3,
+            OpCode::Output => 4,
+            OpCode::JumpIfTrue => 5,
+            OpCode::JumpIfFalse => 6,
+            OpCode::LessThan => 7,
+            OpCode::Equals => 8,
+            OpCode::AdjustRelativeBase => 9,
+            OpCode::Exit => 99,
+            OpCode::Invalid(n) => n,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::intcode::instruction::*;
+
+    #[test]
+    fn test_instruction_from_int() {
+        let instruction = Instruction {
+            code: OpCode::Multiply,
+            parameter_modes: [
+                ParamMode::Immediate,
+                ParamMode::Immediate,
+                ParamMode::Positional,
+            ],
+            raw: 1102,
+        };
+
+        assert_eq!(instruction, Instruction::from_int(1102));
+        assert_eq!(ParamMode::Positional, instruction.get_param_mode(2));
+        assert_eq!(
+            ParamMode::Invalid(3),
+            Instruction::from_int(30001).get_param_mode(2)
+        );
+        assert_eq!(OpCode::Exit, Instruction::from_int(99).code());
+        assert_eq!(1102, instruction.to_int());
+        assert_eq!(21107, Instruction::from_int(21107).to_int());
+
+        // would truncate to 1
+        assert_eq!(
+            OpCode::Invalid(95),
+            Instruction::from_word(&-4_294_967_295i64).code()
+        );
+        assert_eq!(
+            OpCode::Invalid(95),
+            Instruction::from_word(&4_294_967_297i64).code()
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use crate::intcode::Word;
+
+use std::collections::VecDeque;
+use std::io::{BufRead, Write};
+use std::sync::mpsc::{Receiver, Sender};
+
+/// A source of values for the input instruction (code 3).
+pub trait Input<W> {
+    /// Take the next value. Returns None if no value is available.
+    fn read(&mut self) -> Option<W>;
+}
+
+/// A sink for the values emitted by the output instruction (code 4).
+pub trait Output<W> {
+    fn write(&mut self, value: W);
+}
+
+impl<W> Input<W> for VecDeque<W> {
+    fn read(&mut self) -> Option<W> {
+        self.pop_front()
+    }
+}
+
+impl<W> Output<W> for VecDeque<W> {
+    fn write(&mut self, value: W) {
+        self.push_back(value)
+    }
+}
+
+impl<W> Output<W> for Vec<W> {
+    fn write(&mut self, value: W) {
+        self.push(value)
+    }
+}
+
+/// Never provides any input.
+impl<W> Input<W> for () {
+    fn read(&mut self) -> Option<W> {
+        None
+    }
+}
+
+/// Discards all output.
+impl<W> Output<W> for () {
+    fn write(&mut self, _value: W) {}
+}
+
+/// Blocks until a value is received. Returns None once the sending side hung up.
+impl<W> Input<W> for Receiver<W> {
+    fn read(&mut self) -> Option<W> {
+        self.recv().ok()
+    }
+}
+
+/// Values sent after the receiving side hung up are discarded.
+impl<W> Output<W> for Sender<W> {
+    fn write(&mut self, value: W) {
+        let _ = self.send(value);
+    }
+}
+
+/// Reads input values from an iterator.
+pub struct IterInput<I>(pub I);
+
+impl<W, I: Iterator<Item = W>> Input<W> for IterInput<I> {
+    fn read(&mut self) -> Option<W> {
+        self.0.next()
+    }
+}
+
+/// Reads input values by calling a closure.
+pub struct InputFn<F>(pub F);
+
+impl<W, F: FnMut() -> Option<W>> Input<W> for InputFn<F> {
+    fn read(&mut self) -> Option<W> {
+        (self.0)()
+    }
+}
+
+/// Passes output values to a closure.
+pub struct OutputFn<F>(pub F);
+
+impl<W, F: FnMut(W)> Output<W> for OutputFn<F> {
+    fn write(&mut self, value: W) {
+        (self.0)(value)
+    }
+}
+
+/// Prompts for input on stdin and prints output to stdout.
+pub struct Stdio;
+
+/// Prompts again until a line parses as a value. Returns None at the end of stdin or
+/// if reading it fails.
+impl<W: Word> Input<W> for Stdio {
+    fn read(&mut self) -> Option<W> {
+        prompt(std::io::stdin().lock(), std::io::stdout())
+    }
+}
+
+impl<W: Word> Output<W> for Stdio {
+    fn write(&mut self, value: W) {
+        println!("IntCodeVM: {}", value);
+    }
+}
+
+// ask for a value until a line of the input parses as one
+fn prompt<W: Word>(input: impl BufRead, mut output: impl Write) -> Option<W> {
+    let _ = writeln!(output, "[IntCodeVM] input required:");
+
+    for line in input.lines() {
+        match line.ok()?.trim().parse() {
+            Ok(value) => return Some(value),
+            Err(_) => {
+                let _ = writeln!(output, "[IntCodeVM] not a number, try again:");
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::intcode::io::*;
+
+    #[test]
+    fn test_prompt() {
+        let mut output = Vec::new();
+        let value: Option<i64> = prompt(&b"x\n 12 \n13\n"[..], &mut output);
+
+        assert_eq!(Some(12), value);
+        assert_eq!(
+            "[IntCodeVM] input required:\n[IntCodeVM] not a number, try again:\n",
+            String::from_utf8(output).unwrap()
+        );
+        assert_eq!(None, prompt::<i64>(&b"x\n"[..], Vec::new()));
+        assert_eq!(None, prompt::<i64>(&b"\xff\n1\n"[..], Vec::new()));
+    }
+}
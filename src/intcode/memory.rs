@@ -0,0 +1,242 @@
+use crate::intcode::Word;
+
+use std::collections::HashMap;
+
+/// Number of cells per page of paged memory.
+const PAGE_SIZE: usize = 1024;
+
+/// Default limit for dense memory, large enough for any puzzle program.
+pub const DEFAULT_MAX_MEMORY: usize = 1 << 24;
+
+/// Default limit for paged memory. Only written pages are allocated, so the limit
+/// is far beyond `DEFAULT_MAX_MEMORY`, but it still keeps addresses bounded.
+pub const DEFAULT_MAX_PAGED_MEMORY: usize = 1 << 40;
+
+/// Memory of the VM. It grows on demand and cells that were never written read as
+/// zero. Accessing a cell at or beyond the maximum size fails instead of allocating,
+/// the VM reports it as `IntCodeError::OutOfBounds`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Memory<W> {
+    store: Store<W>,
+    max_size: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Store<W> {
+    /// one contiguous block, extended up to the highest written address
+    Dense(Vec<W>),
+    /// fixed-size pages, allocated on the first write to any of their cells
+    Paged(HashMap<usize, Vec<W>>),
+}
+
+impl<W: Word> Memory<W> {
+    /// Contiguous memory starting with the given cells, limited to `DEFAULT_MAX_MEMORY`.
+    pub fn dense(cells: Vec<W>) -> Self {
+        Self {
+            store: Store::Dense(cells),
+            max_size: DEFAULT_MAX_MEMORY,
+        }
+    }
+
+    /// Sparse memory starting with the given cells, limited to
+    /// `DEFAULT_MAX_PAGED_MEMORY`. Only pages that were written are allocated, so
+    /// programs may address cells far beyond their image.
+    pub fn paged(cells: Vec<W>) -> Self {
+        let mut memory = Self {
+            store: Store::Paged(HashMap::new()),
+            max_size: DEFAULT_MAX_PAGED_MEMORY,
+        };
+
+        for (position, value) in cells.into_iter().enumerate() {
+            assert!(memory.set(position, value));
+        }
+
+        memory
+    }
+
+    /// Limit the memory to the given number of cells.
+    pub fn with_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// Returns the number of cells currently backed by storage.
+    pub fn len(&self) -> usize {
+        match &self.store {
+            Store::Dense(cells) => cells.len(),
+            Store::Paged(pages) => pages.len() * PAGE_SIZE,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of cells that would be backed by storage after writing
+    /// to the given position.
+    pub fn len_after_write(&self, position: usize) -> usize {
+        match &self.store {
+            Store::Dense(cells) => cells.len().max(position.saturating_add(1)),
+            Store::Paged(pages) if pages.contains_key(&(position / PAGE_SIZE)) => self.len(),
+            Store::Paged(_) => self.len() + PAGE_SIZE,
+        }
+    }
+
+    /// Copy the cells from address zero up to the end of the highest allocated cell
+    /// or page. For paged memory that may be far more than is allocated, use `blocks`
+    /// or `cells` to visit only the allocated cells.
+    pub fn to_vec(&self) -> Vec<W> {
+        match &self.store {
+            Store::Dense(cells) => cells.clone(),
+            Store::Paged(pages) => {
+                let end = pages.keys().max().map_or(0, |page| (page + 1) * PAGE_SIZE);
+                (0..end)
+                    .map(|position| self.get(position).unwrap())
+                    .collect()
+            }
+        }
+    }
+
+    pub fn is_paged(&self) -> bool {
+        matches!(self.store, Store::Paged(_))
+    }
+
+    /// The cells of dense memory, None for paged memory.
+    pub fn as_slice(&self) -> Option<&[W]> {
+        match &self.store {
+            Store::Dense(cells) => Some(cells),
+            Store::Paged(_) => None,
+        }
+    }
+
+    /// The allocated cells with their address, in ascending order.
+    pub fn cells(&self) -> impl Iterator<Item = (usize, &W)> + '_ {
+        self.blocks()
+            .into_iter()
+            .flat_map(|(address, cells)| (address..).zip(cells))
+    }
+
+    /// The allocated blocks of cells with their start address, in ascending order.
+    /// Dense memory is a single block, paged memory has a block per page.
+    pub fn blocks(&self) -> Vec<(usize, &[W])> {
+        match &self.store {
+            Store::Dense(cells) => vec![(0, cells.as_slice())],
+            Store::Paged(pages) => {
+                let mut blocks = pages
+                    .iter()
+                    .map(|(page, cells)| (page * PAGE_SIZE, cells.as_slice()))
+                    .collect::<Vec<_>>();
+                blocks.sort_by_key(|&(address, _)| address);
+                blocks
+            }
+        }
+    }
+
+    /// Returns None if the position is at or beyond the maximum size.
+    pub fn get(&self, position: usize) -> Option<W> {
+        if position >= self.max_size {
+            return None;
+        }
+
+        let value = match &self.store {
+            Store::Dense(cells) => cells.get(position),
+            Store::Paged(pages) => pages
+                .get(&(position / PAGE_SIZE))
+                .map(|page| &page[position % PAGE_SIZE]),
+        };
+
+        Some(value.cloned().unwrap_or_else(W::zero))
+    }
+
+    /// Returns false without storing the value if the position is at or beyond the
+    /// maximum size.
+    #[must_use]
+    pub fn set(&mut self, position: usize, value: W) -> bool {
+        if position >= self.max_size {
+            return false;
+        }
+
+        match &mut self.store {
+            Store::Dense(cells) => {
+                if position >= cells.len() {
+                    cells.resize(position + 1, W::zero());
+                }
+
+                cells[position] = value;
+            }
+            Store::Paged(pages) => {
+                pages
+                    .entry(position / PAGE_SIZE)
+                    .or_insert_with(|| vec![W::zero(); PAGE_SIZE])[position % PAGE_SIZE] = value;
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::intcode::memory::*;
+    use crate::intcode::{Instruction, IntCodeError, IntCodeVM};
+
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_memory_grows_on_demand() {
+        let mut vm = IntCodeVM::new(vec![1101, 2, 3, 1000, 4, 1000, 4, 2000, 99]);
+        let mut output = Vec::new();
+        vm.run(&mut VecDeque::new(), &mut output).unwrap();
+
+        assert_eq!(vec![5, 0], output);
+        assert_eq!(1001, vm.memory().len());
+    }
+
+    #[test]
+    fn test_memory_paged() {
+        let mut memory = Memory::paged(vec![1, 2, 3]);
+        assert!(memory.set((1 << 40) - 1, 42));
+        assert!(!memory.set(1 << 40, 42));
+
+        assert_eq!(Some(2), memory.get(1));
+        assert_eq!(Some(0), memory.get(5000));
+        assert_eq!(Some(42), memory.get((1 << 40) - 1));
+        assert_eq!(2 * PAGE_SIZE, memory.len());
+        assert_eq!(2 * PAGE_SIZE, memory.len_after_write(PAGE_SIZE - 1));
+        assert_eq!(3 * PAGE_SIZE, memory.len_after_write(PAGE_SIZE));
+        assert_eq!(None, memory.as_slice());
+
+        let cells = memory.cells().filter(|(_, value)| **value != 0);
+        assert_eq!(
+            vec![(0, &1), (1, &2), (2, &3), ((1 << 40) - 1, &42)],
+            cells.collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_memory_max_size() {
+        let mut memory = Memory::dense(vec![1, 2, 3]).with_max_size(10);
+
+        assert_eq!(3, memory.len_after_write(1));
+        assert_eq!(10, memory.len_after_write(9));
+        assert!(memory.set(9, 1));
+        assert!(!memory.set(10, 1));
+        assert_eq!(None, memory.get(10));
+
+        let mut vm = IntCodeVM::with_memory(Memory::dense(vec![1, 0, 0, 10]).with_max_size(10));
+        let err = vm.run(&mut VecDeque::new(), &mut Vec::new()).unwrap_err();
+
+        assert_eq!(
+            IntCodeError::OutOfBounds {
+                ptr: 0,
+                instruction: Instruction::from_int(1),
+                address: 10
+            },
+            err
+        );
+    }
+}
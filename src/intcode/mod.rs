@@ -0,0 +1,38 @@
+//! The IntCode virtual machine used by the 2019 puzzles.
+
+pub mod ascii;
+pub mod asm;
+#[cfg(feature = "async")]
+mod async_io;
+mod budget;
+mod cache;
+pub mod cfg;
+pub mod compiler;
+pub mod debugger;
+pub mod disasm;
+mod error;
+#[cfg(any(test, feature = "fuzzing"))]
+pub mod fuzz;
+mod instruction;
+mod io;
+mod memory;
+pub mod network;
+mod parse;
+pub mod pipeline;
+pub mod screen;
+pub mod snapshot;
+pub mod symbolic;
+pub mod trace;
+mod vm;
+mod word;
+
+#[cfg(feature = "async")]
+pub use self::async_io::{AsyncError, ASYNC_SLICE};
+pub use self::budget::{Budget, LOOP_DETECTION_START};
+pub use self::error::IntCodeError;
+pub use self::instruction::{Instruction, OpCode, ParamMode};
+pub use self::io::{Input, InputFn, IterInput, Output, OutputFn, Stdio};
+pub use self::memory::{Memory, DEFAULT_MAX_MEMORY, DEFAULT_MAX_PAGED_MEMORY};
+pub use self::parse::ParseError;
+pub use self::vm::{IntCodeVM, Status};
+pub use self::word::Word;
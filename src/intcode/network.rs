@@ -0,0 +1,803 @@
+//! A network of VMs exchanging packets, like the computers of day 23.
+//!
+//! Every machine receives its address as first input. It sends a packet by emitting
+//! the destination address followed by the packet's x and y value and receives one
+//! as x and y on its input, or -1 if no packet is waiting. Packets addressed to no
+//! machine go to the `Supervisor`, which is also told when the network is idle: no
+//! packet is on its way and every machine read -1 at least twice in a row without
+//! sending anything in between.
+
+use crate::intcode::{IntCodeError, IntCodeVM, OpCode, Status, Word};
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::time::Duration;
+
+/// Number of consecutive empty reads after which a machine counts as idle.
+const IDLE_READS: u32 = 2;
+
+/// Number of instructions a machine runs before `run` moves on to the next one and
+/// before a thread of `run_threaded` checks whether the network was stopped.
+const SLICE: u64 = 10_000;
+
+/// How long the router of `run_threaded` waits for packets before checking whether
+/// the network is idle, and how long a machine waits for a packet before it reads -1.
+const IDLE_POLL: Duration = Duration::from_millis(1);
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Packet<W> {
+    /// The sending machine, None for packets sent by the supervisor.
+    pub source: Option<usize>,
+    pub destination: W,
+    pub x: W,
+    pub y: W,
+}
+
+impl<W: Word> Display for Packet<W> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.source {
+            Some(source) => write!(f, "{}", source)?,
+            None => write!(f, "*")?,
+        }
+
+        write!(f, " -> {}: {}, {}", self.destination, self.x, self.y)
+    }
+}
+
+/// What the network should do after the supervisor was called.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Control<W> {
+    Continue,
+    /// Send the given packets, then continue.
+    Send(Vec<Packet<W>>),
+    Stop,
+}
+
+/// Handles packets addressed to no machine and idle networks.
+pub trait Supervisor<W> {
+    fn receive(&mut self, packet: &Packet<W>) -> Control<W>;
+
+    /// Called whenever the network became idle.
+    fn idle(&mut self) -> Control<W>;
+}
+
+/// The NAT of day 23. Remembers the packets sent to address 255 and sends the last
+/// one to machine 0 whenever the network is idle. Stops once it sent the same y
+/// value twice in a row, or when the network is idle and it has nothing to send.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Nat<W> {
+    /// The first packet sent to the NAT.
+    pub first: Option<Packet<W>>,
+    /// The y value that was sent twice in a row.
+    pub repeated: Option<W>,
+    last: Option<Packet<W>>,
+    last_sent: Option<W>,
+}
+
+impl<W: Word> Supervisor<W> for Nat<W> {
+    fn receive(&mut self, packet: &Packet<W>) -> Control<W> {
+        if packet.destination.to_i64() == Some(255) {
+            self.first.get_or_insert_with(|| packet.clone());
+            self.last = Some(packet.clone());
+        }
+
+        Control::Continue
+    }
+
+    fn idle(&mut self) -> Control<W> {
+        let last = match &self.last {
+            Some(last) => last,
+            None => return Control::Stop,
+        };
+
+        if self.last_sent.as_ref() == Some(&last.y) {
+            self.repeated = Some(last.y.clone());
+            return Control::Stop;
+        }
+
+        self.last_sent = Some(last.y.clone());
+
+        Control::Send(vec![Packet {
+            source: None,
+            destination: W::zero(),
+            x: last.x.clone(),
+            y: last.y.clone(),
+        }])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum NetworkError {
+    /// The machine with the given address failed.
+    IntCode { machine: usize, error: IntCodeError },
+}
+
+impl Display for NetworkError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NetworkError::IntCode { machine, error } => write!(f, "machine {}: {}", machine, error),
+        }
+    }
+}
+
+impl Error for NetworkError {}
+
+/// Machines running copies of the same program, addressed from zero.
+#[derive(Debug, Clone)]
+pub struct Network<W: Word = i64> {
+    vms: Vec<IntCodeVM<W>>,
+    // packets waiting to be read by each machine
+    inboxes: Vec<VecDeque<(W, W)>>,
+    // the parts of a packet each machine emitted so far
+    partial: Vec<Vec<W>>,
+    idle_reads: Vec<u32>,
+    log: Option<Vec<Packet<W>>>,
+}
+
+impl<W: Word> Network<W> {
+    /// Start `size` copies of the program, each with its address as first input.
+    pub fn new(program: &IntCodeVM<W>, size: usize) -> Self {
+        let vms = (0..size)
+            .map(|address| {
+                let mut vm = program.clone();
+                vm.push_input(
+                    W::from_i64(address as i64).expect("address doesn't fit into a word"),
+                );
+                vm
+            })
+            .collect();
+
+        Network {
+            vms,
+            inboxes: vec![VecDeque::new(); size],
+            partial: vec![Vec::new(); size],
+            idle_reads: vec![0; size],
+            log: None,
+        }
+    }
+
+    /// Record every packet, see `log`.
+    pub fn with_log(mut self) -> Self {
+        self.log = Some(Vec::new());
+        self
+    }
+
+    /// The packets sent so far in the order they were routed, including those sent
+    /// by and to the supervisor. Empty unless enabled by `with_log`.
+    pub fn log(&self) -> &[Packet<W>] {
+        self.log.as_deref().unwrap_or(&[])
+    }
+
+    pub fn vms(&self) -> &[IntCodeVM<W>] {
+        &self.vms
+    }
+
+    /// Queue a packet for the machine it is addressed to. Returns false if there is
+    /// no such machine.
+    pub fn send(&mut self, packet: Packet<W>) -> bool {
+        match self.machine(&packet.destination) {
+            Some(machine) => {
+                self.record(&packet);
+                self.inboxes[machine].push_back((packet.x, packet.y));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Run the machines round-robin on the current thread, each until it waits for
+    /// input, sent a packet or ran for `SLICE` instructions, until the supervisor
+    /// stops the network or all machines halted.
+    pub fn run(&mut self, supervisor: &mut dyn Supervisor<W>) -> Result<(), NetworkError> {
+        let empty = W::from_i64(-1).expect("words must be signed");
+
+        loop {
+            let mut active = false;
+
+            for machine in 0..self.vms.len() {
+                if self.vms[machine].is_halted() {
+                    continue;
+                }
+
+                if waiting(&self.vms[machine]) {
+                    match self.inboxes[machine].pop_front() {
+                        Some((x, y)) => {
+                            self.vms[machine].push_input(x);
+                            self.vms[machine].push_input(y);
+                            self.idle_reads[machine] = 0;
+                            active = true;
+                        }
+                        None => {
+                            self.vms[machine].push_input(empty.clone());
+                            self.idle_reads[machine] += 1;
+                        }
+                    }
+                }
+
+                loop {
+                    let status = self.vms[machine]
+                        .resume_limited(SLICE)
+                        .map_err(|error| NetworkError::IntCode { machine, error })?;
+
+                    match status {
+                        Some(Status::Output(value)) => {
+                            self.idle_reads[machine] = 0;
+
+                            if let Some(packet) = self.emit(machine, value) {
+                                active = true;
+
+                                if self.route(packet, supervisor) {
+                                    return Ok(());
+                                }
+
+                                break;
+                            }
+                        }
+                        Some(Status::NeedsInput) | Some(Status::Halted) | None => break,
+                    }
+                }
+            }
+
+            if self.vms.iter().all(IntCodeVM::is_halted) {
+                return Ok(());
+            }
+
+            let idle = self.inboxes.iter().all(VecDeque::is_empty)
+                && self
+                    .vms
+                    .iter()
+                    .zip(&self.idle_reads)
+                    .all(|(vm, reads)| vm.is_halted() || (waiting(vm) && *reads >= IDLE_READS));
+
+            if !active && idle {
+                self.idle_reads.iter_mut().for_each(|reads| *reads = 0);
+
+                if self.control(supervisor.idle(), supervisor) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    // collect an output value of the machine, returns the packet once it is complete
+    fn emit(&mut self, machine: usize, value: W) -> Option<Packet<W>> {
+        self.partial[machine].push(value);
+
+        if self.partial[machine].len() < 3 {
+            return None;
+        }
+
+        Some(packet(machine, &mut self.partial[machine]))
+    }
+
+    // deliver a packet to its machine or the supervisor, returns true if the
+    // supervisor stopped the network
+    fn route(&mut self, packet: Packet<W>, supervisor: &mut dyn Supervisor<W>) -> bool {
+        if self.machine(&packet.destination).is_some() {
+            self.send(packet);
+            return false;
+        }
+
+        self.record(&packet);
+        let control = supervisor.receive(&packet);
+
+        self.control(control, supervisor)
+    }
+
+    fn control(&mut self, control: Control<W>, supervisor: &mut dyn Supervisor<W>) -> bool {
+        match control {
+            Control::Continue => false,
+            Control::Send(packets) => packets
+                .into_iter()
+                .any(|packet| self.route(packet, supervisor)),
+            Control::Stop => true,
+        }
+    }
+    fn machine(&self, address: &W) -> Option<usize> {
+        machine(address, self.vms.len())
+    }
+
+    fn record(&mut self, packet: &Packet<W>) {
+        record(&mut self.log, packet);
+    }
+}
+
+impl<W: Word + Send + Sync> Network<W> {
+    /// Like `run`, but every machine runs on its own thread while the supervisor is
+    /// called on the current one. The order of packets depends on the scheduling of
+    /// the threads, the log shows the order they were routed in.
+    pub fn run_threaded(&mut self, supervisor: &mut dyn Supervisor<W>) -> Result<(), NetworkError> {
+        let size = self.vms.len();
+        let stop = AtomicBool::new(false);
+        let activity = Mutex::new(Activity {
+            in_flight: 0,
+            machines: self
+                .vms
+                .iter()
+                .map(|vm| MachineActivity {
+                    halted: vm.is_halted(),
+                    ..MachineActivity::default()
+                })
+                .collect(),
+        });
+
+        let (outbox, packets) = mpsc::channel();
+        let (inboxes, receivers): (Vec<_>, Vec<_>) = (0..size).map(|_| mpsc::channel()).unzip();
+        let mut router = Router {
+            inboxes,
+            activity: &activity,
+            log: &mut self.log,
+        };
+
+        for (machine, queued) in self.inboxes.iter_mut().enumerate() {
+            for (x, y) in queued.drain(..) {
+                router.deliver(machine, x, y);
+            }
+        }
+
+        let vms = &mut self.vms;
+        let partial = &mut self.partial;
+        let mut result = Ok(());
+
+        let receivers = std::thread::scope(|scope| {
+            let handles = vms
+                .iter_mut()
+                .zip(partial.iter_mut())
+                .zip(receivers)
+                .enumerate()
+                .map(|(machine, ((vm, partial), receiver))| {
+                    let outbox = outbox.clone();
+                    let (stop, activity) = (&stop, &activity);
+                    let update = move |f: &dyn Fn(&mut Activity, &mut MachineActivity)| {
+                        let mut activity = activity.lock().unwrap();
+                        let mut state = activity.machines[machine];
+                        f(&mut activity, &mut state);
+                        activity.machines[machine] = state;
+                    };
+
+                    scope.spawn(move || {
+                        let empty = W::from_i64(-1).expect("words must be signed");
+
+                        while !stop.load(Ordering::SeqCst) {
+                            match vm.resume_limited(SLICE) {
+                                Ok(None) => {}
+                                Ok(Some(Status::Output(value))) => {
+                                    partial.push(value);
+
+                                    if partial.len() < 3 {
+                                        update(&|_, state| state.idle_reads = 0);
+                                        continue;
+                                    }
+
+                                    let packet = packet(machine, partial);
+                                    update(&|activity, state| {
+                                        activity.in_flight += 1;
+                                        state.idle_reads = 0;
+                                    });
+                                    let _ = outbox.send(Ok(packet));
+                                }
+                                Ok(Some(Status::NeedsInput)) => {
+                                    update(&|_, state| state.waiting = true);
+
+                                    match receiver.recv_timeout(IDLE_POLL) {
+                                        Ok((x, y)) => {
+                                            update(&|activity, state| {
+                                                activity.in_flight -= 1;
+                                                state.idle_reads = 0;
+                                                state.waiting = false;
+                                            });
+                                            vm.push_input(x);
+                                            vm.push_input(y);
+                                        }
+                                        Err(_) => {
+                                            update(&|_, state| {
+                                                state.idle_reads += 1;
+                                                state.waiting = false;
+                                            });
+                                            vm.push_input(empty.clone());
+                                        }
+                                    }
+                                }
+                                Ok(Some(Status::Halted)) => break,
+                                Err(error) => {
+                                    let _ =
+                                        outbox.send(Err(NetworkError::IntCode { machine, error }));
+                                    break;
+                                }
+                            }
+                        }
+
+                        let halted = vm.is_halted();
+                        update(&|_, state| state.halted = halted);
+                        receiver
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            drop(outbox);
+
+            loop {
+                match packets.recv_timeout(IDLE_POLL) {
+                    Ok(Ok(packet)) => {
+                        router.activity.lock().unwrap().in_flight -= 1;
+
+                        if router.route(packet, supervisor) {
+                            break;
+                        }
+                    }
+                    Ok(Err(error)) => {
+                        result = Err(error);
+                        break;
+                    }
+                    Err(mpsc::RecvTimeoutError::Timeout) => {
+                        if router.activity.lock().unwrap().take_idle()
+                            && router.control(supervisor.idle(), supervisor)
+                        {
+                            break;
+                        }
+                    }
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                }
+            }
+
+            stop.store(true, Ordering::SeqCst);
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        // hand packets that weren't read back to the single-threaded state
+        for (inbox, receiver) in self.inboxes.iter_mut().zip(receivers) {
+            inbox.extend(receiver.try_iter());
+        }
+
+        result
+    }
+}
+
+/// What the threads of `run_threaded` are doing. Kept behind a single lock, so the
+/// router decides whether the network is idle on a consistent snapshot.
+struct Activity {
+    // packets sent to a machine but not read yet, or emitted but not routed yet
+    in_flight: usize,
+    machines: Vec<MachineActivity>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct MachineActivity {
+    // consecutive reads of -1 without a packet or output in between
+    idle_reads: u32,
+    // blocked on a read with no packet waiting
+    waiting: bool,
+    halted: bool,
+}
+
+impl Activity {
+    // returns true if the network is idle and starts counting empty reads anew
+    fn take_idle(&mut self) -> bool {
+        let idle = self.in_flight == 0
+            && self.machines.iter().all(|machine| {
+                machine.halted || (machine.waiting && machine.idle_reads >= IDLE_READS)
+            });
+
+        if idle {
+            self.machines
+                .iter_mut()
+                .for_each(|machine| machine.idle_reads = 0);
+        }
+
+        idle
+    }
+}
+
+/// Routes the packets of `run_threaded` through the machines' channels.
+struct Router<'a, W> {
+    inboxes: Vec<mpsc::Sender<(W, W)>>,
+    activity: &'a Mutex<Activity>,
+    log: &'a mut Option<Vec<Packet<W>>>,
+}
+
+impl<W: Word> Router<'_, W> {
+    // returns true if the supervisor stopped the network
+    fn route(&mut self, packet: Packet<W>, supervisor: &mut dyn Supervisor<W>) -> bool {
+        record(self.log, &packet);
+
+        match machine(&packet.destination, self.inboxes.len()) {
+            Some(machine) => {
+                self.deliver(machine, packet.x, packet.y);
+                false
+            }
+            None => {
+                let control = supervisor.receive(&packet);
+                self.control(control, supervisor)
+            }
+        }
+    }
+
+    fn deliver(&mut self, machine: usize, x: W, y: W) {
+        self.activity.lock().unwrap().in_flight += 1;
+        let _ = self.inboxes[machine].send((x, y));
+    }
+
+    fn control(&mut self, control: Control<W>, supervisor: &mut dyn Supervisor<W>) -> bool {
+        match control {
+            Control::Continue => false,
+            Control::Send(packets) => packets
+                .into_iter()
+                .any(|packet| self.route(packet, supervisor)),
+            Control::Stop => true,
+        }
+    }
+}
+
+// whether the machine is blocked on a read with no input queued
+fn waiting<W: Word>(vm: &IntCodeVM<W>) -> bool {
+    !vm.is_halted()
+        && vm.pending_input().is_empty()
+        && vm.current_instruction().code() == OpCode::Input
+}
+
+// the complete packet emitted by a machine
+fn packet<W: Word>(machine: usize, parts: &mut Vec<W>) -> Packet<W> {
+    let mut parts = parts.drain(..);
+
+    Packet {
+        source: Some(machine),
+        destination: parts.next().unwrap(),
+        x: parts.next().unwrap(),
+        y: parts.next().unwrap(),
+    }
+}
+
+// the machine with the given address in a network of the given size
+fn machine<W: Word>(address: &W, size: usize) -> Option<usize> {
+    address
+        .to_i64()
+        .and_then(|address| std::convert::TryFrom::try_from(address).ok())
+        .filter(|address| *address < size)
+}
+
+fn record<W: Clone>(log: &mut Option<Vec<Packet<W>>>, packet: &Packet<W>) {
+    if let Some(log) = log {
+        log.push(packet.clone());
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::intcode::asm::assemble_to_string;
+    use crate::intcode::network::*;
+
+    // Machine n forwards every packet to machine n + 1 and the last one to 255,
+    // increasing y by one while it's below 4.
+    const RELAY: &str = "
+                IN -> [addr]
+                ADD [addr], #1 -> [next]
+                EQ [next], #3 -> [last]
+                JF [last], #loop
+                ADD #255, #0 -> [next]
+        loop:   IN -> [x]
+                EQ [x], #-1 -> [flag]
+                JT [flag], #loop
+                IN -> [y]
+                LT [y], #4 -> [flag]
+                ADD [y], [flag] -> [y]
+                OUT [next]
+                OUT [x]
+                OUT [y]
+                JT #1, #loop
+        addr:   .data 0
+        next:   .data 0
+        last:   .data 0
+        x:      .data 0
+        y:      .data 0
+        flag:   .data 0
+    ";
+
+    fn network() -> Network {
+        let program: IntCodeVM = assemble_to_string(RELAY).unwrap().parse().unwrap();
+        let mut network = Network::new(&program, 3).with_log();
+
+        assert!(network.send(Packet {
+            source: None,
+            destination: 0,
+            x: 7,
+            y: 0
+        }));
+
+        network
+    }
+
+    fn check(network: &Network, nat: &Nat<i64>) {
+        let first = nat.first.as_ref().unwrap();
+        assert_eq!("2 -> 255: 7, 3", first.to_string());
+        assert_eq!(Some(4), nat.repeated);
+
+        let log = network
+            .log()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(12, log.len());
+        assert_eq!(
+            vec![
+                "* -> 0: 7, 0",
+                "0 -> 1: 7, 1",
+                "1 -> 2: 7, 2",
+                "2 -> 255: 7, 3"
+            ],
+            log[..4]
+        );
+        assert_eq!(
+            vec![
+                "* -> 0: 7, 4",
+                "0 -> 1: 7, 4",
+                "1 -> 2: 7, 4",
+                "2 -> 255: 7, 4"
+            ],
+            log[8..]
+        );
+    }
+
+    #[test]
+    fn test_network_run() {
+        let mut network = network();
+        let mut nat = Nat::default();
+        network.run(&mut nat).unwrap();
+        check(&network, &nat);
+
+        assert!(!network.send(Packet {
+            source: None,
+            destination: 3,
+            x: 0,
+            y: 0
+        }));
+    }
+
+    #[test]
+    fn test_network_run_threaded() {
+        let mut network = network();
+        let mut nat = Nat::default();
+        network.run_threaded(&mut nat).unwrap();
+        check(&network, &nat);
+    }
+
+    // Reads -1 forever, but sends a packet to 255 after every read and a while of
+    // counting down, long enough for the router to check whether it is idle.
+    const CHATTY: &str = "
+        loop:   IN -> [x]
+                ADD #50000, #0 -> [wait]
+        delay:  ADD [wait], #-1 -> [wait]
+                JT [wait], #delay
+                ADD [count], #1 -> [count]
+                OUT #255
+                OUT [x]
+                OUT [count]
+                JT #1, #loop
+        x:      .data 0
+        wait:   .data 0
+        count:  .data 0
+    ";
+
+    /// Stops after the given number of packets, counting idle calls.
+    struct Counter {
+        packets: usize,
+        idle: usize,
+    }
+
+    impl Supervisor<i64> for Counter {
+        fn receive(&mut self, _: &Packet<i64>) -> Control<i64> {
+            self.packets -= 1;
+
+            if self.packets == 0 {
+                Control::Stop
+            } else {
+                Control::Continue
+            }
+        }
+
+        fn idle(&mut self) -> Control<i64> {
+            self.idle += 1;
+            Control::Continue
+        }
+    }
+
+    #[test]
+    fn test_network_sending_is_not_idle() {
+        let program: IntCodeVM = assemble_to_string(CHATTY).unwrap().parse().unwrap();
+
+        for &threaded in &[false, true] {
+            let mut network = Network::new(&program, 2);
+            let mut counter = Counter {
+                packets: 20,
+                idle: 0,
+            };
+
+            if threaded {
+                network.run_threaded(&mut counter).unwrap();
+            } else {
+                network.run(&mut counter).unwrap();
+            }
+
+            assert_eq!(0, counter.idle);
+        }
+    }
+
+    // Machine 0 sends packets to 255 forever, machine 1 sends a single one and
+    // machine 2 loops forever without any I/O.
+    const GREEDY: &str = "
+                IN -> [addr]
+                JT [addr], #other
+        spam:   OUT #255
+                OUT #0
+                OUT #0
+                JT #1, #spam
+        other:  EQ [addr], #1 -> [flag]
+                JF [flag], #spin
+                OUT #255
+                OUT #1
+                OUT #1
+                HLT
+        spin:   JT #1, #spin
+        addr:   .data 0
+        flag:   .data 0
+    ";
+
+    /// Stops at the first packet from machine 1 or after too many packets.
+    #[derive(Default)]
+    struct Patient {
+        packets: usize,
+        found: bool,
+    }
+
+    impl Supervisor<i64> for Patient {
+        fn receive(&mut self, packet: &Packet<i64>) -> Control<i64> {
+            self.packets += 1;
+            self.found = packet.source == Some(1);
+
+            if self.found || self.packets == 1000 {
+                Control::Stop
+            } else {
+                Control::Continue
+            }
+        }
+
+        fn idle(&mut self) -> Control<i64> {
+            Control::Continue
+        }
+    }
+
+    #[test]
+    fn test_network_busy_machines() {
+        let program: IntCodeVM = assemble_to_string(GREEDY).unwrap().parse().unwrap();
+
+        let mut network = Network::new(&program, 3);
+        let mut patient = Patient::default();
+        network.run(&mut patient).unwrap();
+        assert!(patient.found);
+
+        // returns although machine 2 never waits for input
+        let mut network = Network::new(&program, 3);
+        network.run_threaded(&mut Patient::default()).unwrap();
+    }
+
+    #[test]
+    fn test_network_errors() {
+        let program: IntCodeVM = "3,0,98".parse().unwrap();
+        let mut network = Network::new(&program, 2);
+        let err = network.run(&mut Nat::default()).unwrap_err();
+        assert!(matches!(err, NetworkError::IntCode { machine: 0, .. }));
+        assert_eq!(
+            "machine 0: invalid opcode 98 in instruction 98 (???) at position 2",
+            err.to_string()
+        );
+
+        let err = network.run_threaded(&mut Nat::default()).unwrap_err();
+        assert!(matches!(err, NetworkError::IntCode { .. }));
+    }
+}
@@ -0,0 +1,186 @@
+use crate::intcode::{IntCodeVM, Word};
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Why a program couldn't be parsed.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ParseError {
+    /// A cell is not a number that fits into a word. `offset` is the byte offset of
+    /// the token in the input, `index` the number of cells before it.
+    InvalidCell {
+        offset: usize,
+        index: usize,
+        token: String,
+    },
+    /// The input contains nothing but whitespace.
+    Empty,
+    /// Reading the input failed.
+    Io {
+        kind: io::ErrorKind,
+        message: String,
+    },
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::InvalidCell {
+                offset,
+                index,
+                token,
+            } => write!(
+                f,
+                "invalid cell {} at byte {}: {:?} is not a valid number",
+                index, offset, token
+            ),
+            ParseError::Empty => write!(f, "the program is empty"),
+            ParseError::Io { message, .. } => write!(f, "failed to read the program: {}", message),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+impl From<io::Error> for ParseError {
+    fn from(e: io::Error) -> Self {
+        ParseError::Io {
+            kind: e.kind(),
+            message: e.to_string(),
+        }
+    }
+}
+
+/// Parse comma separated cells. Whitespace around cells, including line breaks, is
+/// ignored.
+impl<W: Word> FromStr for IntCodeVM<W> {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_bytes(s.as_bytes())
+    }
+}
+
+impl<W: Word> IntCodeVM<W> {
+    /// Parse a program, see `from_str`.
+    pub fn from_bytes(source: &[u8]) -> Result<Self, ParseError> {
+        Ok(Self::new(parse_program(source)?))
+    }
+
+    /// Read and parse a program, see `from_str`.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self, ParseError> {
+        let mut source = Vec::new();
+        reader.read_to_end(&mut source)?;
+
+        Self::from_bytes(&source)
+    }
+
+    /// Read and parse the program stored in the given file, see `from_str`.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ParseError> {
+        Self::from_reader(File::open(path)?)
+    }
+}
+
+fn parse_program<W: Word>(source: &[u8]) -> Result<Vec<W>, ParseError> {
+    if source.iter().all(u8::is_ascii_whitespace) {
+        return Err(ParseError::Empty);
+    }
+
+    let mut cells = Vec::new();
+    let mut start = 0;
+
+    for (index, cell) in source.split(|&byte| byte == b',').enumerate() {
+        let leading = cell
+            .iter()
+            .take_while(|byte| byte.is_ascii_whitespace())
+            .count();
+        let trailing = cell[leading..]
+            .iter()
+            .rev()
+            .take_while(|byte| byte.is_ascii_whitespace())
+            .count();
+        let token = &cell[leading..cell.len() - trailing];
+        let offset = start + leading;
+        start += cell.len() + 1;
+
+        let value = std::str::from_utf8(token)
+            .ok()
+            .and_then(|token| token.parse().ok())
+            .ok_or_else(|| ParseError::InvalidCell {
+                offset,
+                index,
+                token: String::from_utf8_lossy(token).into_owned(),
+            })?;
+
+        cells.push(value);
+    }
+
+    Ok(cells)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::intcode::parse::*;
+
+    #[test]
+    fn test_parse_whitespace() {
+        let vm: IntCodeVM = " 1,0, 0,\n0 ,99\r\n\n".parse().unwrap();
+
+        assert_eq!(IntCodeVM::new(vec![1, 0, 0, 0, 99]), vm);
+        assert_eq!(Ok(vm), IntCodeVM::from_bytes(b"1,0,0,0,99"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = "1,0,\n 0x,0,99".parse::<IntCodeVM>().unwrap_err();
+
+        assert_eq!(
+            ParseError::InvalidCell {
+                offset: 6,
+                index: 2,
+                token: "0x".to_string()
+            },
+            err
+        );
+        assert_eq!(
+            "invalid cell 2 at byte 6: \"0x\" is not a valid number",
+            err.to_string()
+        );
+        assert_eq!(
+            Err(ParseError::InvalidCell {
+                offset: 4,
+                index: 2,
+                token: "".to_string()
+            }),
+            "1,2,,4".parse::<IntCodeVM>()
+        );
+        assert_eq!(
+            Err(ParseError::InvalidCell {
+                offset: 0,
+                index: 0,
+                token: "3000000000".to_string()
+            }),
+            "3000000000,99".parse::<IntCodeVM<i32>>()
+        );
+        assert_eq!(Err(ParseError::Empty), " \n".parse::<IntCodeVM>());
+    }
+
+    #[test]
+    fn test_parse_reader_and_file() {
+        let vm: IntCodeVM = IntCodeVM::from_reader("1,0,0,0,99\n".as_bytes()).unwrap();
+        assert_eq!(IntCodeVM::new(vec![1, 0, 0, 0, 99]), vm);
+
+        let err = IntCodeVM::<i64>::from_file("/nonexistent/program.txt").unwrap_err();
+        assert!(matches!(
+            err,
+            ParseError::Io {
+                kind: io::ErrorKind::NotFound,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,256 @@
+//! Several VMs wired output to input, like the amplifiers of day 7.
+
+use crate::intcode::{IntCodeError, IntCodeVM, Status, Word};
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::num::NonZeroUsize;
+
+/// How the VMs of a pipeline are connected.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Topology {
+    /// Every VM feeds the next one, the output of the last one is the result.
+    Chain,
+    /// Like a chain, but the output of the last VM is also fed back into the first.
+    Ring,
+}
+
+/// Why a pipeline stopped before all VMs halted.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PipelineError {
+    /// The VM with the given index failed.
+    IntCode { machine: usize, error: IntCodeError },
+    /// All VMs that didn't halt are waiting for input that will never arrive.
+    Deadlock,
+}
+
+impl Display for PipelineError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PipelineError::IntCode { machine, error } => {
+                write!(f, "machine {}: {}", machine, error)
+            }
+            PipelineError::Deadlock => write!(f, "all machines are waiting for input"),
+        }
+    }
+}
+
+impl Error for PipelineError {}
+
+/// VMs connected output to input, run round-robin on the current thread.
+#[derive(Debug, Clone)]
+pub struct Pipeline<W: Word = i64> {
+    vms: Vec<IntCodeVM<W>>,
+    topology: Topology,
+}
+
+impl<W: Word> Pipeline<W> {
+    pub fn new(vms: Vec<IntCodeVM<W>>, topology: Topology) -> Self {
+        Pipeline { vms, topology }
+    }
+
+    /// A copy of the program per phase setting, each receiving its phase setting as
+    /// first input.
+    pub fn with_phases(program: &IntCodeVM<W>, phases: &[W], topology: Topology) -> Self {
+        let vms = phases
+            .iter()
+            .map(|phase| {
+                let mut vm = program.clone();
+                vm.push_input(phase.clone());
+                vm
+            })
+            .collect();
+
+        Self::new(vms, topology)
+    }
+
+    pub fn vms(&self) -> &[IntCodeVM<W>] {
+        &self.vms
+    }
+
+    /// Feed `input` to the first VM and run all VMs in turn, each until it needs
+    /// input or halts, until all of them halted. Returns the last value emitted by
+    /// the last VM.
+    pub fn run(&mut self, input: W) -> Result<Option<W>, PipelineError> {
+        let count = self.vms.len();
+        let mut last = None;
+
+        match self.vms.first_mut() {
+            Some(vm) => vm.push_input(input),
+            None => return Ok(None),
+        }
+
+        loop {
+            let mut emitted = false;
+
+            for machine in 0..count {
+                loop {
+                    let status = self.vms[machine]
+                        .resume()
+                        .map_err(|error| PipelineError::IntCode { machine, error })?;
+
+                    match status {
+                        Status::Output(value) => {
+                            emitted = true;
+
+                            if machine + 1 < count {
+                                self.vms[machine + 1].push_input(value);
+                            } else {
+                                if self.topology == Topology::Ring {
+                                    self.vms[0].push_input(value.clone());
+                                }
+
+                                last = Some(value);
+                            }
+                        }
+                        Status::NeedsInput | Status::Halted => break,
+                    }
+                }
+            }
+
+            if self.vms.iter().all(IntCodeVM::is_halted) {
+                return Ok(last);
+            }
+
+            if !emitted {
+                return Err(PipelineError::Deadlock);
+            }
+        }
+    }
+}
+
+/// The phase settings and the output of the best run, see `best_phases`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Best<W> {
+    pub phases: Vec<W>,
+    pub output: W,
+}
+
+/// Run a pipeline for every permutation of the phase settings in parallel and
+/// return the permutation producing the highest output. Ties go to the permutation
+/// that comes first in lexicographic order of the positions in `phases`. Fails with
+/// the error of the first failing permutation in that order.
+pub fn best_phases<W: Word + Send + Sync>(
+    program: &IntCodeVM<W>,
+    phases: &[W],
+    topology: Topology,
+    input: W,
+) -> Result<Option<Best<W>>, PipelineError> {
+    let permutations = permutations(phases);
+    let threads = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    let chunk_size = permutations.len().div_ceil(threads).max(1);
+
+    let outputs = std::thread::scope(|scope| {
+        permutations
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let input = input.clone();
+
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|phases| {
+                            Pipeline::with_phases(program, phases, topology).run(input.clone())
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    let mut best: Option<Best<W>> = None;
+
+    for (phases, output) in permutations.into_iter().zip(outputs) {
+        if let Some(output) = output? {
+            if best.as_ref().is_none_or(|best| output > best.output) {
+                best = Some(Best { phases, output });
+            }
+        }
+    }
+
+    Ok(best)
+}
+
+// all orderings of the given values, in lexicographic order of their positions
+fn permutations<W: Clone>(values: &[W]) -> Vec<Vec<W>> {
+    if values.is_empty() {
+        return vec![Vec::new()];
+    }
+
+    (0..values.len())
+        .flat_map(|i| {
+            let mut rest = values.to_vec();
+            let first = rest.remove(i);
+
+            permutations(&rest).into_iter().map(move |mut permutation| {
+                permutation.insert(0, first.clone());
+                permutation
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::intcode::pipeline::*;
+
+    const CHAIN: &str = "3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0";
+    const RING: &str = "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,\
+        1005,28,6,99,0,0,5";
+
+    #[test]
+    fn test_pipeline_chain_and_ring() {
+        let program: IntCodeVM = CHAIN.parse().unwrap();
+        let mut pipeline = Pipeline::with_phases(&program, &[4, 3, 2, 1, 0], Topology::Chain);
+        assert_eq!(Ok(Some(43210)), pipeline.run(0));
+        assert!(pipeline.vms().iter().all(IntCodeVM::is_halted));
+
+        let program: IntCodeVM = RING.parse().unwrap();
+        let mut pipeline = Pipeline::with_phases(&program, &[9, 8, 7, 6, 5], Topology::Ring);
+        assert_eq!(Ok(Some(139_629_729)), pipeline.run(0));
+    }
+
+    #[test]
+    fn test_pipeline_errors() {
+        // the second machine waits for a second input the first never sends
+        let program: IntCodeVM = "3,0,4,0,3,0,99".parse().unwrap();
+        let mut pipeline = Pipeline::new(vec![program.clone(), program], Topology::Chain);
+        assert_eq!(Err(PipelineError::Deadlock), pipeline.run(1));
+
+        let program: IntCodeVM = "3,0,4,0,99".parse().unwrap();
+        let mut pipeline = Pipeline::new(vec![program, "98".parse().unwrap()], Topology::Chain);
+        let err = pipeline.run(1).unwrap_err();
+        assert!(matches!(err, PipelineError::IntCode { machine: 1, .. }));
+        assert_eq!(
+            "machine 1: invalid opcode 98 in instruction 98 (???) at position 0",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_pipeline_best_phases() {
+        assert_eq!(vec![vec![1, 2], vec![2, 1]], permutations(&[1, 2]));
+        assert_eq!(120, permutations(&[0, 1, 2, 3, 4]).len());
+
+        let program: IntCodeVM = CHAIN.parse().unwrap();
+        assert_eq!(
+            Ok(Some(Best {
+                phases: vec![4, 3, 2, 1, 0],
+                output: 43210
+            })),
+            best_phases(&program, &[0, 1, 2, 3, 4], Topology::Chain, 0)
+        );
+
+        let program: IntCodeVM = RING.parse().unwrap();
+        assert_eq!(
+            Ok(Some(Best {
+                phases: vec![9, 8, 7, 6, 5],
+                output: 139_629_729
+            })),
+            best_phases(&program, &[5, 6, 7, 8, 9], Topology::Ring, 0)
+        );
+    }
+}
@@ -0,0 +1,394 @@
+//! A framebuffer for programs that draw by emitting (x, y, tile) triples, like the
+//! arcade cabinet of day 13.
+//!
+//! Triples addressed to a channel, e.g. the score at x = -1, y = 0, update the value
+//! of the channel instead of a tile.
+//!
+//! Stray tiles far away from the others would make the drawn area huge, so the
+//! output is cropped to the `MAX_SCREEN_SIZE` columns and rows holding the most
+//! tiles unless configured otherwise.
+
+use crate::intcode::{Output, Word};
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::io::{self, Write};
+
+/// Default number of columns and rows a screen renders at most.
+pub const MAX_SCREEN_SIZE: usize = 1024;
+
+/// How tiles are rendered, indexed by tile value.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Palette {
+    entries: Vec<(char, [u8; 3])>,
+}
+
+impl Palette {
+    /// Characters and RGB colors of the tiles 0, 1, 2 and so on. Tile 0 is also
+    /// used for cells that were never drawn.
+    pub fn new(entries: Vec<(char, [u8; 3])>) -> Self {
+        Palette { entries }
+    }
+
+    /// Empty, wall, block, paddle and ball.
+    pub fn arcade() -> Self {
+        Palette::new(vec![
+            (' ', [0, 0, 0]),
+            ('#', [128, 128, 128]),
+            ('*', [0, 160, 255]),
+            ('-', [255, 255, 255]),
+            ('o', [255, 80, 0]),
+        ])
+    }
+
+    /// Tiles the palette doesn't cover are rendered as '?' in magenta.
+    fn get<W: Word>(&self, tile: &W) -> (char, [u8; 3]) {
+        tile.to_i64()
+            .and_then(|tile| std::convert::TryFrom::try_from(tile).ok())
+            .and_then(|tile: usize| self.entries.get(tile))
+            .copied()
+            .unwrap_or(('?', [255, 0, 255]))
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::arcade()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Screen<W: Word = i64> {
+    tiles: BTreeMap<(i64, i64), W>,
+    // name, position and last value of every channel
+    channels: Vec<(String, (i64, i64), Option<W>)>,
+    palette: Palette,
+    // maximum width and height of the rendered area
+    max_size: (usize, usize),
+    partial: Vec<W>,
+}
+
+impl<W: Word> Screen<W> {
+    pub fn new() -> Self {
+        Screen {
+            tiles: BTreeMap::new(),
+            channels: Vec::new(),
+            palette: Palette::default(),
+            max_size: (MAX_SCREEN_SIZE, MAX_SCREEN_SIZE),
+            partial: Vec::new(),
+        }
+    }
+
+    /// The screen of the arcade cabinet, with the score channel at x = -1, y = 0.
+    pub fn arcade() -> Self {
+        Screen::new().with_channel("score", -1, 0)
+    }
+
+    /// Treat the position as a channel with the given name, see `channel`.
+    pub fn with_channel(mut self, name: &str, x: i64, y: i64) -> Self {
+        self.channels.push((name.to_string(), (x, y), None));
+        self
+    }
+
+    pub fn with_palette(mut self, palette: Palette) -> Self {
+        self.palette = palette;
+        self
+    }
+
+    /// Render at most `width` columns and `height` rows of the drawn area, those
+    /// with the most tiles drawn.
+    pub fn with_max_size(mut self, width: usize, height: usize) -> Self {
+        self.max_size = (width, height);
+        self
+    }
+
+    /// Set the tile at the given position, or the value of the channel there.
+    pub fn draw(&mut self, x: i64, y: i64, tile: W) {
+        match self.channels.iter_mut().find(|(_, at, _)| *at == (x, y)) {
+            Some((_, _, value)) => *value = Some(tile),
+            None => {
+                self.tiles.insert((x, y), tile);
+            }
+        }
+    }
+
+    pub fn tile(&self, x: i64, y: i64) -> Option<&W> {
+        self.tiles.get(&(x, y))
+    }
+
+    /// All drawn tiles by position.
+    pub fn tiles(&self) -> &BTreeMap<(i64, i64), W> {
+        &self.tiles
+    }
+
+    /// The last value written to the channel, None if there is no such channel or
+    /// nothing was written to it yet.
+    pub fn channel(&self, name: &str) -> Option<&W> {
+        self.channels
+            .iter()
+            .find(|(channel, _, _)| channel == name)
+            .and_then(|(_, _, value)| value.as_ref())
+    }
+
+    /// The smallest and largest coordinates of all drawn tiles, or None if no tile was
+    /// drawn yet.
+    pub fn bounds(&self) -> Option<((i64, i64), (i64, i64))> {
+        let mut positions = self.tiles.keys();
+        let &(x, y) = positions.next()?;
+
+        Some(
+            positions.fold(((x, y), (x, y)), |((x0, y0), (x1, y1)), &(x, y)| {
+                ((x0.min(x), y0.min(y)), (x1.max(x), y1.max(y)))
+            }),
+        )
+    }
+
+    // the rows of the drawn area cropped to the maximum size, each tile rendered by
+    // `render`
+    fn rows<T>(&self, render: impl Fn(Option<&W>) -> T) -> Vec<Vec<T>> {
+        if self.tiles.is_empty() {
+            return Vec::new();
+        }
+
+        let (width, height) = self.max_size;
+        let (x0, x1) = densest(self.tiles.keys().map(|&(x, _)| x).collect(), width);
+        let (y0, y1) = densest(self.tiles.keys().map(|&(_, y)| y).collect(), height);
+
+        (y0..=y1)
+            .take(height)
+            .map(|y| {
+                (x0..=x1)
+                    .take(width)
+                    .map(|x| render(self.tile(x, y)))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Clear the terminal and draw the screen, see `snapshot`.
+    pub fn render<O: Write>(&self, mut out: O) -> io::Result<()> {
+        write!(out, "\x1b[H\x1b[2J{}", self.snapshot())?;
+        out.flush()
+    }
+
+    /// The drawn area as text using the characters of the palette, followed by a
+    /// line per channel with its name and value.
+    pub fn snapshot(&self) -> String {
+        let mut lines = self
+            .rows(|tile| self.palette.get(tile.unwrap_or(&W::zero())).0)
+            .into_iter()
+            .map(|row| row.into_iter().collect::<String>())
+            .collect::<Vec<_>>();
+
+        for (name, _, value) in &self.channels {
+            match value {
+                Some(value) => lines.push(format!("{}: {}", name, value)),
+                None => lines.push(format!("{}: -", name)),
+            }
+        }
+
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+
+    /// Write the drawn area as plain PPM image, one pixel per tile in the color of
+    /// the palette.
+    pub fn write_ppm<O: Write>(&self, mut out: O) -> io::Result<()> {
+        let rows = self.rows(|tile| self.palette.get(tile.unwrap_or(&W::zero())).1);
+        writeln!(out, "P3\n{} {}\n255", width(&rows), rows.len())?;
+
+        for row in rows {
+            let pixels = row
+                .iter()
+                .map(|[r, g, b]| format!("{} {} {}", r, g, b))
+                .collect::<Vec<_>>();
+
+            writeln!(out, "{}", pixels.join("  "))?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the drawn area as plain PBM image, black for every tile other than 0.
+    pub fn write_pbm<O: Write>(&self, mut out: O) -> io::Result<()> {
+        let rows = self.rows(|tile| tile.is_some_and(|tile| *tile != W::zero()));
+        writeln!(out, "P1\n{} {}", width(&rows), rows.len())?;
+
+        for row in rows {
+            let pixels = row
+                .iter()
+                .map(|black| if *black { "1" } else { "0" })
+                .collect::<Vec<_>>();
+
+            writeln!(out, "{}", pixels.join(" "))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<W: Word> Default for Screen<W> {
+    fn default() -> Self {
+        Screen::new()
+    }
+}
+
+impl<W: Word> Display for Screen<W> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.snapshot())
+    }
+}
+
+/// Consumes (x, y, tile) triples. Triples with coordinates that don't fit into an
+/// i64 are dropped.
+impl<W: Word> Output<W> for Screen<W> {
+    fn write(&mut self, value: W) {
+        self.partial.push(value);
+
+        if self.partial.len() < 3 {
+            return;
+        }
+
+        let tile = self.partial.pop().unwrap();
+        let y = self.partial.pop().unwrap();
+        let x = self.partial.pop().unwrap();
+
+        if let (Some(x), Some(y)) = (x.to_i64(), y.to_i64()) {
+            self.draw(x, y, tile);
+        }
+    }
+}
+
+fn width<T>(rows: &[Vec<T>]) -> usize {
+    rows.first().map_or(0, Vec::len)
+}
+
+// the first and last of the coordinates within the range of `size` consecutive
+// values that holds the most of them, the earliest such range on a tie
+fn densest(mut coordinates: Vec<i64>, size: usize) -> (i64, i64) {
+    coordinates.sort_unstable();
+
+    let (mut best, mut first, mut last) = (0, 0, 0);
+    let mut end = 0;
+
+    for start in 0..coordinates.len() {
+        end = end.max(start);
+
+        while end < coordinates.len()
+            && i128::from(coordinates[end]) - i128::from(coordinates[start]) < size as i128
+        {
+            end += 1;
+        }
+
+        if end - start > best {
+            best = end - start;
+            first = start;
+            last = end - 1;
+        }
+    }
+
+    (coordinates[first], coordinates[last])
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::intcode::screen::*;
+    use crate::intcode::IntCodeVM;
+
+    // draws a wall, a block, the paddle and the ball, then sets the score
+    const ARCADE: &str = "104,0,104,0,104,1,104,1,104,0,104,1,104,2,104,0,104,1,\
+        104,0,104,1,104,2,104,1,104,1,104,3,104,2,104,2,104,4,104,-1,104,0,104,12345,99";
+
+    fn screen() -> Screen {
+        let mut vm: IntCodeVM = ARCADE.parse().unwrap();
+        let mut screen = Screen::arcade();
+        vm.run(&mut (), &mut screen).unwrap();
+
+        screen
+    }
+
+    #[test]
+    fn test_screen_snapshot() {
+        let screen = screen();
+
+        assert_eq!(Some(&12345), screen.channel("score"));
+        assert_eq!(None, screen.tile(-1, 0));
+        assert_eq!(Some(((0, 0), (2, 2))), screen.bounds());
+        assert_eq!("###\n*- \n  o\nscore: 12345\n", screen.snapshot());
+
+        let mut screen: Screen = Screen::new().with_channel("score", -1, 0);
+        assert_eq!("score: -\n", screen.to_string());
+        assert_eq!(None, screen.bounds());
+
+        screen.draw(-2, 3, 7);
+        assert_eq!("?\nscore: -\n", screen.to_string());
+
+        let mut out = Vec::new();
+        screen.render(&mut out).unwrap();
+        assert_eq!(
+            "\x1b[H\x1b[2J?\nscore: -\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_screen_images() {
+        let screen = screen();
+
+        let mut out = Vec::new();
+        screen.write_pbm(&mut out).unwrap();
+        assert_eq!(
+            "P1\n3 3\n1 1 1\n1 1 0\n0 0 1\n",
+            String::from_utf8(out).unwrap()
+        );
+
+        let mut out = Vec::new();
+        screen
+            .with_palette(Palette::new(vec![(' ', [0, 0, 0]), ('#', [255, 255, 255])]))
+            .write_ppm(&mut out)
+            .unwrap();
+        assert_eq!(
+            "P3\n3 3\n255\n\
+             255 255 255  255 255 255  255 255 255\n\
+             255 0 255  255 0 255  0 0 0\n\
+             0 0 0  0 0 0  255 0 255\n",
+            String::from_utf8(out).unwrap()
+        );
+
+        let mut out = Vec::new();
+        Screen::<i64>::new().write_pbm(&mut out).unwrap();
+        assert_eq!("P1\n0 0\n", String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn test_screen_cropped() {
+        let mut screen = screen().with_max_size(2, 1);
+        assert_eq!("##\nscore: 12345\n", screen.snapshot());
+
+        // stray tiles would otherwise render an area of 2^128 tiles
+        screen.draw(i64::MIN, i64::MIN, 1);
+        screen.draw(i64::MAX, i64::MAX, 1);
+        let screen = screen.with_max_size(MAX_SCREEN_SIZE, MAX_SCREEN_SIZE);
+
+        let mut out = Vec::new();
+        screen.write_pbm(&mut out).unwrap();
+        assert_eq!(
+            "P1\n3 3\n1 1 1\n1 1 0\n0 0 1\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_screen_outlier() {
+        // the crop follows the drawn picture, not the stray tile
+        let mut screen = screen().with_max_size(4, 4);
+        screen.draw(i64::MIN, i64::MIN, 1);
+        assert_eq!(Some(((i64::MIN, i64::MIN), (2, 2))), screen.bounds());
+        assert_eq!("###\n*- \n  o\nscore: 12345\n", screen.snapshot());
+
+        // without a denser area the leftmost and topmost tiles are shown
+        let mut screen: Screen = Screen::new();
+        screen.draw(i64::MIN, 0, 1);
+        screen.draw(i64::MAX, 0, 1);
+        assert_eq!("#\n", screen.snapshot());
+    }
+}
@@ -0,0 +1,296 @@
+//! Saving and restoring the complete state of the IntCode VM.
+//!
+//! Snapshots are plain text, so they can be inspected and attached to bug reports:
+//!
+//! ```text
+//! IntCodeVM snapshot v1
+//! ptr: 2
+//! relative base: 0
+//! halted: false
+//! input: 7,8
+//! memory: dense 16777216
+//! block 0: 3,9,8,9,10,9,4,9,99,-1,8
+//! ```
+//!
+//! The memory line names the memory layout and its maximum size, every block line
+//! holds the cells of an allocated block starting at the given address.
+
+use crate::intcode::{IntCodeVM, Memory, Word};
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// Version of the snapshot format written by `IntCodeVM::write_snapshot`.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+const HEADER: &str = "IntCodeVM snapshot v";
+
+impl<W: Word> IntCodeVM<W> {
+    /// Write the memory, pointer, relative base, pending input and halted flag.
+    pub fn write_snapshot<O: Write>(&self, mut out: O) -> io::Result<()> {
+        let memory = self.memory();
+
+        writeln!(out, "{}{}", HEADER, SNAPSHOT_VERSION)?;
+        writeln!(out, "ptr: {}", self.ptr())?;
+        writeln!(out, "relative base: {}", self.relative_base())?;
+        writeln!(out, "halted: {}", self.is_halted())?;
+        writeln!(out, "input: {}", join(self.pending_input()))?;
+        writeln!(
+            out,
+            "memory: {} {}",
+            if memory.is_paged() { "paged" } else { "dense" },
+            memory.max_size()
+        )?;
+
+        for (address, cells) in memory.blocks() {
+            writeln!(out, "block {}: {}", address, join(cells))?;
+        }
+
+        Ok(())
+    }
+
+    /// Restore a VM written by `write_snapshot`.
+    pub fn read_snapshot<R: BufRead>(input: R) -> io::Result<Self> {
+        let mut lines = input.lines();
+
+        let header = lines.next().transpose()?.unwrap_or_default();
+        let version = header
+            .strip_prefix(HEADER)
+            .and_then(|version| version.parse::<u32>().ok())
+            .ok_or_else(|| invalid_data(1, "not an IntCodeVM snapshot"))?;
+
+        if version != SNAPSHOT_VERSION {
+            return Err(invalid_data(
+                1,
+                &format!("unsupported snapshot version {}", version),
+            ));
+        }
+
+        let mut ptr = None;
+        let mut relative_base = None;
+        let mut halted = None;
+        let mut pending_input = None;
+        let mut memory = None;
+
+        for (index, line) in lines.enumerate() {
+            let line = line?;
+            let number = index + 2;
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once(':')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| invalid_data(number, &line))?;
+
+            let parsed = match key.split_whitespace().collect::<Vec<_>>().as_slice() {
+                ["ptr"] => value.parse().ok().map(|value| ptr = Some(value)),
+                ["relative", "base"] => value.parse().ok().map(|value| relative_base = Some(value)),
+                ["halted"] => value.parse().ok().map(|value| halted = Some(value)),
+                ["input"] => parse_cells(value).map(|values| pending_input = Some(values)),
+                ["memory"] => parse_memory(value).map(|value| memory = Some(value)),
+                ["block", address] => {
+                    match (
+                        memory.as_mut(),
+                        address.parse::<usize>(),
+                        parse_cells(value),
+                    ) {
+                        (Some(memory), Ok(address), Some(cells)) => {
+                            load_block(memory, address, cells)
+                        }
+                        _ => None,
+                    }
+                }
+                _ => None,
+            };
+
+            if parsed.is_none() {
+                return Err(invalid_data(number, &line));
+            }
+        }
+
+        match (ptr, relative_base, halted, pending_input, memory) {
+            (Some(ptr), Some(relative_base), Some(halted), Some(pending_input), Some(memory)) => {
+                let mut vm = IntCodeVM::with_memory(memory);
+                vm.jmp(ptr);
+                vm.set_relative_base(relative_base);
+                vm.set_halted(halted);
+
+                for value in pending_input {
+                    vm.push_input(value);
+                }
+
+                Ok(vm)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "incomplete IntCodeVM snapshot",
+            )),
+        }
+    }
+
+    /// Write a snapshot to the given file, replacing it if it exists.
+    pub fn save_snapshot<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write_snapshot(&mut out)?;
+
+        out.flush()
+    }
+
+    /// Restore a VM from a snapshot file.
+    pub fn load_snapshot<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::read_snapshot(BufReader::new(File::open(path)?))
+    }
+}
+
+fn join<'a, W: Word + 'a>(cells: impl IntoIterator<Item = &'a W>) -> String {
+    cells
+        .into_iter()
+        .map(W::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn parse_cells<W: Word>(value: &str) -> Option<Vec<W>> {
+    if value.is_empty() {
+        return Some(Vec::new());
+    }
+
+    value
+        .split(',')
+        .map(|cell| cell.trim().parse().ok())
+        .collect()
+}
+
+fn parse_memory<W: Word>(value: &str) -> Option<Memory<W>> {
+    let (layout, max_size) = value.split_once(' ')?;
+    let max_size = max_size.trim().parse().ok()?;
+
+    match layout {
+        "dense" => Some(Memory::dense(Vec::new()).with_max_size(max_size)),
+        "paged" => Some(Memory::paged(Vec::new()).with_max_size(max_size)),
+        _ => None,
+    }
+}
+
+// Dense memory is written as a single block from address zero, so a dense block
+// has to continue the cells loaded so far. Otherwise a short line could allocate
+// memory up to the maximum size named by the snapshot.
+fn load_block<W: Word>(memory: &mut Memory<W>, address: usize, cells: Vec<W>) -> Option<()> {
+    if !memory.is_paged() && address > memory.len() {
+        return None;
+    }
+
+    cells
+        .into_iter()
+        .enumerate()
+        .try_for_each(|(offset, cell)| {
+            let position = address.checked_add(offset)?;
+            memory.set(position, cell).then_some(())
+        })
+}
+
+fn invalid_data(line: usize, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {}: invalid snapshot: {}", line, message),
+    )
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::intcode::snapshot::*;
+    use crate::intcode::Status;
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut vm: IntCodeVM = "3,9,8,9,10,9,4,9,99,-1,8".parse().unwrap();
+        vm.push_input(7);
+        vm.push_input(8);
+        vm.set_relative_base(-3);
+
+        let mut snapshot = Vec::new();
+        vm.write_snapshot(&mut snapshot).unwrap();
+
+        assert_eq!(
+            "IntCodeVM snapshot v1\n\
+             ptr: 0\n\
+             relative base: -3\n\
+             halted: false\n\
+             input: 7,8\n\
+             memory: dense 16777216\n\
+             block 0: 3,9,8,9,10,9,4,9,99,-1,8\n",
+            String::from_utf8(snapshot.clone()).unwrap()
+        );
+
+        let mut restored = IntCodeVM::read_snapshot(snapshot.as_slice()).unwrap();
+        assert_eq!(vm, restored);
+        assert_eq!(Status::Output(0), restored.resume().unwrap());
+        assert_eq!(Status::Halted, restored.resume().unwrap());
+        assert_eq!(
+            vec![&8],
+            restored.pending_input().iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_snapshot_paged_memory() {
+        let mut vm = IntCodeVM::with_memory(Memory::paged(vec![1101, 1, 2, 5000, 99]));
+        vm.run(&mut (), &mut ()).unwrap();
+
+        let mut snapshot = Vec::new();
+        vm.write_snapshot(&mut snapshot).unwrap();
+        let restored: IntCodeVM = IntCodeVM::read_snapshot(snapshot.as_slice()).unwrap();
+
+        assert_eq!(vm, restored);
+        assert!(restored.is_halted());
+        assert_eq!(Ok(3), restored.read_int(5000));
+    }
+
+    #[test]
+    fn test_snapshot_file() {
+        let path = std::env::temp_dir().join(format!("intcode-{}.snapshot", std::process::id()));
+        let vm: IntCodeVM = "1,0,0,0,99".parse().unwrap();
+
+        vm.save_snapshot(&path).unwrap();
+        let restored = IntCodeVM::load_snapshot(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(vm, restored.unwrap());
+    }
+
+    #[test]
+    fn test_snapshot_errors() {
+        let read = |snapshot: &str| {
+            IntCodeVM::<i64>::read_snapshot(snapshot.as_bytes())
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!(
+            "line 1: invalid snapshot: unsupported snapshot version 2",
+            read("IntCodeVM snapshot v2\n")
+        );
+        assert_eq!(
+            "line 3: invalid snapshot: relative base: x",
+            read("IntCodeVM snapshot v1\nptr: 0\nrelative base: x\n")
+        );
+        assert_eq!(
+            "line 3: invalid snapshot: block 18446744073709551615: 1,2",
+            read("IntCodeVM snapshot v1\nmemory: paged 18446744073709551615\nblock 18446744073709551615: 1,2\n")
+        );
+        assert_eq!(
+            "line 4: invalid snapshot: block 1000000: 1",
+            read(
+                "IntCodeVM snapshot v1\nmemory: dense 1000000000\nblock 0: 99\nblock 1000000: 1\n"
+            )
+        );
+        assert_eq!(
+            "incomplete IntCodeVM snapshot",
+            read("IntCodeVM snapshot v1\nptr: 0\n")
+        );
+    }
+}
@@ -0,0 +1,869 @@
+//! Symbolic execution of IntCode programs.
+//!
+//! Memory cells and inputs can be replaced by named symbols. Arithmetic and
+//! comparisons on symbols build expression trees instead of values, so the final
+//! state of the program describes its result as a function of the symbols, which
+//! `solve` inverts. Control flow, write addresses and the relative base have to stay
+//! concrete, reads through symbolic addresses are recorded as loads.
+
+use crate::intcode::{Instruction, IntCodeError, IntCodeVM, OpCode, ParamMode, Word};
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+
+/// Number of instructions `SymbolicVM::run` executes before giving up by default.
+pub const DEFAULT_MAX_STEPS: u64 = 1_000_000;
+
+/// The value of a memory cell or output in terms of the symbols.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Expr<W> {
+    Const(W),
+    Symbol(String),
+    Add(Box<Expr<W>>, Box<Expr<W>>),
+    Multiply(Box<Expr<W>>, Box<Expr<W>>),
+    LessThan(Box<Expr<W>>, Box<Expr<W>>),
+    Equals(Box<Expr<W>>, Box<Expr<W>>),
+    /// A read from a symbolic address of the memory as it was at the time of the read.
+    Load {
+        memory: Rc<BTreeMap<usize, Expr<W>>>,
+        address: Box<Expr<W>>,
+    },
+}
+
+impl<W: Word> Expr<W> {
+    pub fn symbol(name: &str) -> Self {
+        Expr::Symbol(name.to_string())
+    }
+
+    /// Returns the value if the expression doesn't depend on any symbol.
+    pub fn as_const(&self) -> Option<&W> {
+        match self {
+            Expr::Const(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The names of all symbols the expression depends on.
+    pub fn symbols(&self) -> BTreeSet<String> {
+        let mut symbols = BTreeSet::new();
+        self.collect_symbols(&mut symbols);
+
+        symbols
+    }
+
+    /// Evaluate the expression with the given symbol values. Returns None if a symbol
+    /// has no value, the arithmetic overflows or a load is out of bounds.
+    pub fn eval(&self, values: &BTreeMap<String, W>) -> Option<W> {
+        match self {
+            Expr::Const(value) => Some(value.clone()),
+            Expr::Symbol(name) => values.get(name).cloned(),
+            Expr::Add(lhs, rhs) => lhs.eval(values)?.checked_add(&rhs.eval(values)?),
+            Expr::Multiply(lhs, rhs) => lhs.eval(values)?.checked_mul(&rhs.eval(values)?),
+            Expr::LessThan(lhs, rhs) => Some(bool_word(lhs.eval(values)? < rhs.eval(values)?)),
+            Expr::Equals(lhs, rhs) => Some(bool_word(lhs.eval(values)? == rhs.eval(values)?)),
+            Expr::Load { memory, address } => {
+                let address = to_address(&address.eval(values)?)?;
+
+                match memory.get(&address) {
+                    Some(cell) => cell.eval(values),
+                    None => Some(W::zero()),
+                }
+            }
+        }
+    }
+
+    /// Returns the expression as a sum of symbols multiplied by constants, if it is
+    /// one and its coefficients fit into an i128.
+    pub fn linear(&self) -> Option<Linear> {
+        match self {
+            Expr::Const(value) => Some(Linear {
+                terms: BTreeMap::new(),
+                constant: i128::from(value.to_i64()?),
+            }),
+            Expr::Symbol(name) => Some(Linear {
+                terms: vec![(name.clone(), 1)].into_iter().collect(),
+                constant: 0,
+            }),
+            Expr::Add(lhs, rhs) => lhs.linear()?.add(&rhs.linear()?),
+            Expr::Multiply(lhs, rhs) => {
+                let (lhs, rhs) = (lhs.linear()?, rhs.linear()?);
+
+                if lhs.terms.is_empty() {
+                    rhs.scale(lhs.constant)
+                } else if rhs.terms.is_empty() {
+                    lhs.scale(rhs.constant)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn collect_symbols(&self, symbols: &mut BTreeSet<String>) {
+        match self {
+            Expr::Const(_) => {}
+            Expr::Symbol(name) => {
+                symbols.insert(name.clone());
+            }
+            Expr::Add(lhs, rhs)
+            | Expr::Multiply(lhs, rhs)
+            | Expr::LessThan(lhs, rhs)
+            | Expr::Equals(lhs, rhs) => {
+                lhs.collect_symbols(symbols);
+                rhs.collect_symbols(symbols);
+            }
+            Expr::Load { memory, address } => {
+                address.collect_symbols(symbols);
+
+                for cell in memory.values() {
+                    cell.collect_symbols(symbols);
+                }
+            }
+        }
+    }
+
+    // build lhs + rhs, folding constants. Returns None on overflow.
+    fn add(lhs: Self, rhs: Self) -> Option<Self> {
+        match (lhs, rhs) {
+            (Expr::Const(lhs), Expr::Const(rhs)) => Some(Expr::Const(lhs.checked_add(&rhs)?)),
+            (Expr::Const(zero), expr) | (expr, Expr::Const(zero)) if zero == W::zero() => {
+                Some(expr)
+            }
+            (lhs, rhs) => Some(Expr::Add(Box::new(lhs), Box::new(rhs))),
+        }
+    }
+
+    // build lhs * rhs, folding constants. Returns None on overflow.
+    fn multiply(lhs: Self, rhs: Self) -> Option<Self> {
+        match (lhs, rhs) {
+            (Expr::Const(lhs), Expr::Const(rhs)) => Some(Expr::Const(lhs.checked_mul(&rhs)?)),
+            (Expr::Const(zero), _) | (_, Expr::Const(zero)) if zero == W::zero() => {
+                Some(Expr::Const(zero))
+            }
+            (Expr::Const(one), expr) | (expr, Expr::Const(one)) if one == W::one() => Some(expr),
+            (lhs, rhs) => Some(Expr::Multiply(Box::new(lhs), Box::new(rhs))),
+        }
+    }
+
+    fn less_than(lhs: Self, rhs: Self) -> Self {
+        match (lhs, rhs) {
+            (Expr::Const(lhs), Expr::Const(rhs)) => Expr::Const(bool_word(lhs < rhs)),
+            (lhs, rhs) => Expr::LessThan(Box::new(lhs), Box::new(rhs)),
+        }
+    }
+
+    fn equals(lhs: Self, rhs: Self) -> Self {
+        match (lhs, rhs) {
+            (Expr::Const(lhs), Expr::Const(rhs)) => Expr::Const(bool_word(lhs == rhs)),
+            (lhs, rhs) => Expr::Equals(Box::new(lhs), Box::new(rhs)),
+        }
+    }
+}
+
+impl<W: Word> Display for Expr<W> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Const(value) => write!(f, "{}", value),
+            Expr::Symbol(name) => write!(f, "{}", name),
+            Expr::Add(lhs, rhs) => write!(f, "({} + {})", lhs, rhs),
+            Expr::Multiply(lhs, rhs) => write!(f, "({} * {})", lhs, rhs),
+            Expr::LessThan(lhs, rhs) => write!(f, "({} < {})", lhs, rhs),
+            Expr::Equals(lhs, rhs) => write!(f, "({} == {})", lhs, rhs),
+            Expr::Load { address, .. } => write!(f, "mem[{}]", address),
+        }
+    }
+}
+
+/// A linear combination of symbols, see `Expr::linear`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Linear {
+    /// Coefficients by symbol, never zero.
+    pub terms: BTreeMap<String, i128>,
+    pub constant: i128,
+}
+
+impl Linear {
+    fn add(&self, other: &Linear) -> Option<Linear> {
+        let mut terms = self.terms.clone();
+
+        for (name, coefficient) in &other.terms {
+            let sum = i128::checked_add(*terms.get(name).unwrap_or(&0), *coefficient)?;
+
+            if sum == 0 {
+                terms.remove(name);
+            } else {
+                terms.insert(name.clone(), sum);
+            }
+        }
+
+        Some(Linear {
+            terms,
+            constant: self.constant.checked_add(other.constant)?,
+        })
+    }
+
+    fn scale(&self, factor: i128) -> Option<Linear> {
+        if factor == 0 {
+            return Some(Linear {
+                terms: BTreeMap::new(),
+                constant: 0,
+            });
+        }
+
+        Some(Linear {
+            terms: self
+                .terms
+                .iter()
+                .map(|(name, coefficient)| {
+                    Some((name.clone(), i128::checked_mul(*coefficient, factor)?))
+                })
+                .collect::<Option<_>>()?,
+            constant: self.constant.checked_mul(factor)?,
+        })
+    }
+}
+
+impl Display for Linear {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut parts = self
+            .terms
+            .iter()
+            .map(|(name, coefficient)| match coefficient {
+                1 => name.clone(),
+                _ => format!("{} * {}", coefficient, name),
+            })
+            .collect::<Vec<_>>();
+
+        if self.constant != 0 || parts.is_empty() {
+            parts.push(self.constant.to_string());
+        }
+
+        write!(f, "{}", parts.join(" + "))
+    }
+}
+
+/// Why symbolic execution failed.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SymbolicError {
+    /// A jump, a write address, the relative base or an executed instruction depends
+    /// on a symbol.
+    Symbolic {
+        ptr: usize,
+        instruction: Instruction,
+        expression: String,
+    },
+    /// The program fails no matter the values of the symbols.
+    IntCode(IntCodeError),
+}
+
+impl Display for SymbolicError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SymbolicError::Symbolic {
+                ptr,
+                instruction,
+                expression,
+            } => write!(
+                f,
+                "{} depends on symbols in instruction {} ({}) at position {}",
+                expression,
+                instruction.to_int(),
+                instruction.code().mnemonic(),
+                ptr
+            ),
+            SymbolicError::IntCode(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for SymbolicError {}
+
+impl From<IntCodeError> for SymbolicError {
+    fn from(e: IntCodeError) -> Self {
+        SymbolicError::IntCode(e)
+    }
+}
+
+/// A VM whose memory cells and inputs are expressions.
+#[derive(Debug, Clone)]
+pub struct SymbolicVM<W: Word = i64> {
+    // cells missing from the map are zero, shared with the loads recorded from it
+    // until the next write
+    memory: Rc<BTreeMap<usize, Expr<W>>>,
+    max_memory: usize,
+    ptr: usize,
+    relative_base: W,
+    input: VecDeque<Expr<W>>,
+    output: Vec<Expr<W>>,
+    max_steps: u64,
+}
+
+impl<W: Word> SymbolicVM<W> {
+    /// Start from the current memory of the given VM.
+    pub fn new(vm: &IntCodeVM<W>) -> Self {
+        SymbolicVM {
+            memory: Rc::new(
+                vm.memory()
+                    .cells()
+                    .filter(|(_, value)| **value != W::zero())
+                    .map(|(address, value)| (address, Expr::Const(value.clone())))
+                    .collect(),
+            ),
+            max_memory: vm.memory().max_size(),
+            ptr: vm.ptr(),
+            relative_base: vm.relative_base().clone(),
+            input: VecDeque::new(),
+            output: Vec::new(),
+            max_steps: DEFAULT_MAX_STEPS,
+        }
+    }
+
+    /// Fail with `IntCodeError::StepLimitExceeded` after `steps` instructions.
+    pub fn with_max_steps(mut self, steps: u64) -> Self {
+        self.max_steps = steps;
+        self
+    }
+
+    /// Replace the memory cell at the given address with a symbol.
+    pub fn set_symbol(&mut self, address: usize, name: &str) -> Result<(), IntCodeError> {
+        self.write(address, Expr::symbol(name))
+    }
+
+    /// Queue a value or symbol for the next input instruction.
+    pub fn push_input(&mut self, value: Expr<W>) {
+        self.input.push_back(value)
+    }
+
+    /// The memory cell at the given address, None if it is out of bounds.
+    pub fn read(&self, address: usize) -> Option<Expr<W>> {
+        self.cell(address).ok()
+    }
+
+    /// The cells that were set or written, with their address in ascending order.
+    /// All other cells are zero.
+    pub fn cells(&self) -> impl Iterator<Item = (usize, &Expr<W>)> {
+        self.memory.iter().map(|(address, cell)| (*address, cell))
+    }
+
+    /// Everything the program emitted so far.
+    pub fn output(&self) -> &[Expr<W>] {
+        &self.output
+    }
+
+    pub fn ptr(&self) -> usize {
+        self.ptr
+    }
+
+    /// Run until an exit instruction is reached.
+    pub fn run(&mut self) -> Result<(), SymbolicError> {
+        for _ in 0..self.max_steps {
+            if !self.run_instruction()? {
+                return Ok(());
+            }
+        }
+
+        Err(IntCodeError::StepLimitExceeded {
+            ptr: self.ptr,
+            instruction: self.current_instruction(),
+            limit: self.max_steps,
+        }
+        .into())
+    }
+
+    // run the next instruction, returns false once an exit instruction is reached
+    fn run_instruction(&mut self) -> Result<bool, SymbolicError> {
+        let cell = self.cell(self.ptr)?;
+        let instruction = Instruction::from_word(self.concrete(&cell)?);
+
+        match instruction.code() {
+            OpCode::Add | OpCode::Multiply | OpCode::LessThan | OpCode::Equals => {
+                let lhs = self.parameter(instruction, 0)?;
+                let rhs = self.parameter(instruction, 1)?;
+                let dst = self.write_address(instruction, 2)?;
+
+                let result = match instruction.code() {
+                    OpCode::Add => Expr::add(lhs, rhs),
+                    OpCode::Multiply => Expr::multiply(lhs, rhs),
+                    OpCode::LessThan => Some(Expr::less_than(lhs, rhs)),
+                    _ => Some(Expr::equals(lhs, rhs)),
+                }
+                .ok_or_else(|| self.arithmetic_overflow())?;
+
+                self.write(dst, result)?;
+                self.ptr += 4;
+            }
+            OpCode::Input => {
+                let dst = self.write_address(instruction, 0)?;
+                let value = self.input.pop_front().ok_or(IntCodeError::InputExhausted {
+                    ptr: self.ptr,
+                    instruction,
+                })?;
+
+                self.write(dst, value)?;
+                self.ptr += 2;
+            }
+            OpCode::Output => {
+                let value = self.parameter(instruction, 0)?;
+
+                self.output.push(value);
+                self.ptr += 2;
+            }
+            OpCode::JumpIfTrue | OpCode::JumpIfFalse => {
+                let condition = self.parameter(instruction, 0)?;
+                let condition = self.concrete(&condition)?.clone();
+                let destination = self.parameter(instruction, 1)?;
+                let destination = self.address(self.concrete(&destination)?)?;
+
+                if (condition != W::zero()) == (instruction.code() == OpCode::JumpIfTrue) {
+                    self.ptr = destination;
+                } else {
+                    self.ptr += 3;
+                }
+            }
+            OpCode::AdjustRelativeBase => {
+                let offset = self.parameter(instruction, 0)?;
+                let offset = self.concrete(&offset)?.clone();
+
+                self.relative_base = self
+                    .relative_base
+                    .checked_add(&offset)
+                    .ok_or_else(|| self.arithmetic_overflow())?;
+                self.ptr += 2;
+            }
+            OpCode::Exit => return Ok(false),
+            OpCode::Invalid(_) => {
+                return Err(IntCodeError::InvalidOpCode {
+                    ptr: self.ptr,
+                    instruction,
+                }
+                .into())
+            }
+        }
+
+        Ok(true)
+    }
+
+    // the value of the i-th parameter of the current instruction
+    fn parameter(&self, instruction: Instruction, i: usize) -> Result<Expr<W>, SymbolicError> {
+        let raw = self.cell(self.ptr + 1 + i)?;
+
+        match instruction.get_param_mode(i) {
+            ParamMode::Immediate => Ok(raw),
+            ParamMode::Positional => self.load(raw),
+            ParamMode::Relative => {
+                let address = Expr::add(Expr::Const(self.relative_base.clone()), raw)
+                    .ok_or_else(|| self.arithmetic_overflow())?;
+
+                self.load(address)
+            }
+            ParamMode::Invalid(_) => Err(IntCodeError::InvalidParameterMode {
+                ptr: self.ptr,
+                instruction,
+                parameter: i,
+            }
+            .into()),
+        }
+    }
+
+    // the address the i-th parameter of the current instruction writes to
+    fn write_address(&self, instruction: Instruction, i: usize) -> Result<usize, SymbolicError> {
+        let raw = self.cell(self.ptr + 1 + i)?;
+        let raw = self.concrete(&raw)?;
+
+        match instruction.get_param_mode(i) {
+            ParamMode::Positional => Ok(self.address(raw)?),
+            ParamMode::Relative => Ok(self.address(
+                &self
+                    .relative_base
+                    .checked_add(raw)
+                    .ok_or_else(|| self.arithmetic_overflow())?,
+            )?),
+            ParamMode::Immediate => Err(IntCodeError::ImmediateWrite {
+                ptr: self.ptr,
+                instruction,
+                parameter: i,
+            }
+            .into()),
+            ParamMode::Invalid(_) => Err(IntCodeError::InvalidParameterMode {
+                ptr: self.ptr,
+                instruction,
+                parameter: i,
+            }
+            .into()),
+        }
+    }
+
+    // read the cell at the given address, symbolic addresses are recorded as loads
+    fn load(&self, address: Expr<W>) -> Result<Expr<W>, SymbolicError> {
+        match address {
+            Expr::Const(address) => Ok(self.cell(self.address(&address)?)?),
+            address => Ok(Expr::Load {
+                memory: Rc::clone(&self.memory),
+                address: Box::new(address),
+            }),
+        }
+    }
+
+    fn cell(&self, address: usize) -> Result<Expr<W>, IntCodeError> {
+        if address >= self.max_memory {
+            return Err(self.out_of_bounds(address));
+        }
+
+        Ok(self
+            .memory
+            .get(&address)
+            .cloned()
+            .unwrap_or(Expr::Const(W::zero())))
+    }
+
+    fn write(&mut self, address: usize, value: Expr<W>) -> Result<(), IntCodeError> {
+        if address >= self.max_memory {
+            return Err(self.out_of_bounds(address));
+        }
+
+        Rc::make_mut(&mut self.memory).insert(address, value);
+
+        Ok(())
+    }
+
+    fn concrete<'a>(&self, expr: &'a Expr<W>) -> Result<&'a W, SymbolicError> {
+        expr.as_const().ok_or_else(|| SymbolicError::Symbolic {
+            ptr: self.ptr,
+            instruction: self.current_instruction(),
+            expression: expr.to_string(),
+        })
+    }
+
+    fn address(&self, value: &W) -> Result<usize, IntCodeError> {
+        if *value < W::zero() {
+            return Err(IntCodeError::NegativeAddress {
+                ptr: self.ptr,
+                instruction: self.current_instruction(),
+                address: value.to_string(),
+            });
+        }
+
+        Ok(to_address(value).unwrap_or(usize::MAX))
+    }
+
+    fn current_instruction(&self) -> Instruction {
+        match self.memory.get(&self.ptr) {
+            Some(Expr::Const(value)) => Instruction::from_word(value),
+            _ => Instruction::from_int(0),
+        }
+    }
+
+    fn out_of_bounds(&self, address: usize) -> IntCodeError {
+        IntCodeError::OutOfBounds {
+            ptr: self.ptr,
+            instruction: self.current_instruction(),
+            address,
+        }
+    }
+
+    fn arithmetic_overflow(&self) -> IntCodeError {
+        IntCodeError::ArithmeticOverflow {
+            ptr: self.ptr,
+            instruction: self.current_instruction(),
+        }
+    }
+}
+
+/// Find values for the symbols of `expr` within the given domains so that it
+/// evaluates to `target`. Returns the first solution in the order of the domains,
+/// i.e. the same one a nested loop over the domains would find, or None if there is
+/// none or a symbol has no domain.
+///
+/// Linear expressions are solved for their last symbol directly, everything else is
+/// evaluated for every combination of values. Either way a solution is only returned
+/// if `expr` evaluates to `target` with it.
+pub fn solve<W: Word>(
+    expr: &Expr<W>,
+    target: &W,
+    domains: &[(&str, RangeInclusive<i64>)],
+) -> Option<BTreeMap<String, W>> {
+    let symbols = expr.symbols();
+
+    if symbols
+        .iter()
+        .any(|symbol| domains.iter().all(|(name, _)| name != symbol))
+    {
+        return None;
+    }
+
+    let to_solution = |values: &[i64]| {
+        domains
+            .iter()
+            .zip(values)
+            .map(|((name, _), value)| Some((name.to_string(), W::from_i64(*value)?)))
+            .collect::<Option<BTreeMap<_, _>>>()
+    };
+
+    let mut solution = None;
+    let mut check = |values: &[i64]| {
+        solution = to_solution(values).filter(|values| expr.eval(values).as_ref() == Some(target));
+        solution.is_some()
+    };
+
+    // the linear form is computed in i128, candidates are evaluated in W in case
+    // that overflows
+    if let (Some(linear), Some(target)) = (expr.linear(), target.to_i64()) {
+        solve_linear(&linear, i128::from(target), domains, &mut check);
+    } else {
+        assignments(
+            &domains
+                .iter()
+                .map(|(_, domain)| domain.clone())
+                .collect::<Vec<_>>(),
+            &mut check,
+        );
+    }
+
+    solution
+}
+
+// solve for the last symbol with a coefficient, enumerating all others, until
+// `check` accepts a candidate
+fn solve_linear(
+    linear: &Linear,
+    target: i128,
+    domains: &[(&str, RangeInclusive<i64>)],
+    check: &mut dyn FnMut(&[i64]) -> bool,
+) {
+    let coefficient = |i: usize| linear.terms.get(domains[i].0).copied().unwrap_or(0);
+    let target = match target.checked_sub(linear.constant) {
+        Some(target) => target,
+        None => return,
+    };
+    let solved = (0..domains.len()).rposition(|i| coefficient(i) != 0);
+    let others = (0..domains.len())
+        .filter(|i| Some(*i) != solved)
+        .collect::<Vec<_>>();
+    assignments(
+        &others
+            .iter()
+            .map(|i| domains[*i].1.clone())
+            .collect::<Vec<_>>(),
+        &mut |values| {
+            let rest = others
+                .iter()
+                .zip(values)
+                .try_fold(target, |rest, (i, value)| {
+                    rest.checked_sub(coefficient(*i).checked_mul(i128::from(*value))?)
+                });
+            let mut values = values.to_vec();
+
+            let candidate = match (solved, rest) {
+                (None, Some(0)) => Some(values),
+                (Some(i), Some(rest)) if rest % coefficient(i) == 0 => {
+                    std::convert::TryFrom::try_from(rest / coefficient(i))
+                        .ok()
+                        .filter(|value| domains[i].1.contains(value))
+                        .map(|value| {
+                            values.insert(i, value);
+                            values
+                        })
+                }
+                _ => None,
+            };
+
+            candidate.is_some_and(|values| check(&values))
+        },
+    );
+}
+
+// call `f` with every combination of values from the given domains in lexicographic
+// order until it returns true
+fn assignments(domains: &[RangeInclusive<i64>], f: &mut dyn FnMut(&[i64]) -> bool) {
+    if domains.iter().any(|domain| domain.is_empty()) {
+        return;
+    }
+
+    let mut values = domains
+        .iter()
+        .map(|domain| *domain.start())
+        .collect::<Vec<_>>();
+
+    loop {
+        if f(&values) {
+            return;
+        }
+
+        let mut i = values.len();
+
+        loop {
+            if i == 0 {
+                return;
+            }
+
+            i -= 1;
+
+            if values[i] < *domains[i].end() {
+                values[i] += 1;
+                break;
+            }
+
+            values[i] = *domains[i].start();
+        }
+    }
+}
+
+fn bool_word<W: Word>(value: bool) -> W {
+    if value {
+        W::one()
+    } else {
+        W::zero()
+    }
+}
+
+fn to_address<W: Word>(value: &W) -> Option<usize> {
+    value
+        .to_i64()
+        .and_then(|i| std::convert::TryFrom::try_from(i).ok())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::intcode::symbolic::*;
+
+    // a day 2 style program computing ((noun + verb) * 7) + 1 with a read through
+    // the symbolic noun and verb addresses first
+    const PROGRAM: &str = "1,0,0,3,1,1,2,3,2,3,17,3,1,3,18,0,99,7,1";
+
+    #[test]
+    fn test_symbolic_expressions() {
+        let vm: IntCodeVM = PROGRAM.parse().unwrap();
+        let mut symbolic = SymbolicVM::new(&vm);
+        symbolic.set_symbol(1, "noun").unwrap();
+        symbolic.set_symbol(2, "verb").unwrap();
+        symbolic.run().unwrap();
+
+        let result = symbolic.read(0).unwrap();
+        assert_eq!("(((noun + verb) * 7) + 1)", result.to_string());
+        assert_eq!(
+            "7 * noun + 7 * verb + 1",
+            result.linear().unwrap().to_string()
+        );
+        assert_eq!(
+            "(mem[noun] + mem[verb])",
+            Expr::<i64>::add(
+                symbolic.load(Expr::symbol("noun")).unwrap(),
+                symbolic.load(Expr::symbol("verb")).unwrap()
+            )
+            .unwrap()
+            .to_string()
+        );
+
+        let values = vec![("noun".to_string(), 4), ("verb".to_string(), 2)]
+            .into_iter()
+            .collect();
+        assert_eq!(Some(43), result.eval(&values));
+    }
+
+    #[test]
+    fn test_symbolic_solve() {
+        let vm: IntCodeVM = PROGRAM.parse().unwrap();
+        let mut symbolic = SymbolicVM::new(&vm);
+        symbolic.set_symbol(1, "noun").unwrap();
+        symbolic.set_symbol(2, "verb").unwrap();
+        symbolic.run().unwrap();
+
+        let result = symbolic.read(0).unwrap();
+        let domains = [("noun", 0..=99), ("verb", 0..=99)];
+        let solution = solve(&result, &701, &domains).unwrap();
+
+        // noun 0 would need verb 100
+        assert_eq!(1, solution["noun"]);
+        assert_eq!(99, solution["verb"]);
+        assert_eq!(None, solve(&result, &700, &domains));
+        assert_eq!(None, solve(&result, &701, &domains[..1]));
+
+        // the same search without the linear shortcut
+        let square = Expr::multiply(result.clone(), Expr::symbol("noun")).unwrap();
+        assert_eq!(None, square.linear());
+        let solution = solve(&square, &(701 * 2), &domains).unwrap();
+        assert_eq!((2, 98), (solution["noun"], solution["verb"]));
+
+        // linear in i128, but the sum overflows an i32 for any noun but 0
+        let scaled = |factor: i32| Expr::multiply(Expr::symbol("noun"), Expr::Const(factor));
+        let expr = vec![
+            scaled(1 << 30),
+            scaled(1 << 30),
+            scaled(-(1 << 30)),
+            scaled(-(1 << 30)),
+        ]
+        .into_iter()
+        .try_fold(Expr::symbol("verb"), |sum, term| Expr::add(term?, sum))
+        .unwrap();
+        assert!(expr.linear().is_some());
+        assert_eq!(None, solve(&expr, &5, &[("noun", 1..=3), ("verb", 0..=9)]));
+
+        let solution = solve(&expr, &5, &[("noun", 0..=3), ("verb", 0..=9)]).unwrap();
+        assert_eq!((0, 5), (solution["noun"], solution["verb"]));
+    }
+
+    #[test]
+    fn test_symbolic_input_and_comparisons() {
+        // prints input < 8 and input == 8
+        let vm: IntCodeVM = "3,15,7,15,16,17,4,17,8,15,16,17,4,17,99,0,8,0"
+            .parse()
+            .unwrap();
+        let mut symbolic = SymbolicVM::new(&vm);
+        symbolic.push_input(Expr::symbol("x"));
+        symbolic.run().unwrap();
+
+        let output = symbolic
+            .output()
+            .iter()
+            .map(|expr| expr.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["(x < 8)", "(x == 8)"], output);
+
+        let solution = solve(&symbolic.output()[1], &1, &[("x", -10..=10)]).unwrap();
+        assert_eq!(8, solution["x"]);
+    }
+
+    #[test]
+    fn test_symbolic_errors() {
+        // jumps if the input is non-zero
+        let vm: IntCodeVM = "3,7,1005,7,6,99,99,0".parse().unwrap();
+        let mut symbolic = SymbolicVM::new(&vm);
+        symbolic.push_input(Expr::symbol("x"));
+
+        let err = symbolic.run().unwrap_err();
+        assert_eq!(
+            SymbolicError::Symbolic {
+                ptr: 2,
+                instruction: Instruction::from_int(1005),
+                expression: "x".to_string()
+            },
+            err
+        );
+        assert_eq!(
+            "x depends on symbols in instruction 1005 (JT) at position 2",
+            err.to_string()
+        );
+
+        let mut symbolic = SymbolicVM::new(&vm);
+        assert_eq!(
+            Err(SymbolicError::IntCode(IntCodeError::InputExhausted {
+                ptr: 0,
+                instruction: Instruction::from_int(3)
+            })),
+            symbolic.run()
+        );
+
+        let vm: IntCodeVM = "1105,1,0".parse().unwrap();
+        assert!(matches!(
+            SymbolicVM::new(&vm).with_max_steps(10).run(),
+            Err(SymbolicError::IntCode(IntCodeError::StepLimitExceeded {
+                limit: 10,
+                ..
+            }))
+        ));
+    }
+}
@@ -0,0 +1,847 @@
+//! Execution traces of the IntCode VM.
+//!
+//! A `Tracer` passed to `IntCodeVM::run_traced` is called once for every executed
+//! instruction. `Trace` records the steps, exports them as JSON lines or in a compact
+//! binary format and reconstructs the VM state at any recorded step.
+
+use crate::intcode::{Instruction, IntCodeError, IntCodeVM, OpCode, Word};
+
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::io::{self, BufRead, Read, Write};
+
+/// Magic bytes and version at the start of a binary trace.
+const BINARY_HEADER: &[u8; 5] = b"ICTR\x01";
+
+/// Number of steps a `History` keeps by default.
+pub const DEFAULT_HISTORY_LIMIT: usize = 1 << 20;
+
+/// Receives every instruction executed by `IntCodeVM::run_instruction_traced`.
+pub trait Tracer<W> {
+    fn trace(&mut self, step: Step<W>);
+}
+
+/// Ignores all steps.
+impl<W> Tracer<W> for () {
+    fn trace(&mut self, _step: Step<W>) {}
+}
+
+/// Passes steps to a closure.
+pub struct TracerFn<F>(pub F);
+
+impl<W, F: FnMut(Step<W>)> Tracer<W> for TracerFn<F> {
+    fn trace(&mut self, step: Step<W>) {
+        (self.0)(step)
+    }
+}
+
+/// A memory cell changed by an instruction.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MemoryWrite<W> {
+    pub address: usize,
+    pub old: W,
+    pub new: W,
+}
+
+/// A single executed instruction.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Step<W> {
+    /// Address of the instruction.
+    pub ptr: usize,
+    pub instruction: Instruction,
+    /// Values of the parameters the instruction reads, with their modes resolved.
+    pub operands: Vec<W>,
+    pub writes: Vec<MemoryWrite<W>>,
+    /// The instruction pointer after the instruction.
+    pub next_ptr: usize,
+    /// The relative base after the instruction.
+    pub relative_base: W,
+    pub output: Option<W>,
+}
+
+impl<W: Word> Step<W> {
+    // capture everything known about the instruction at the pointer before it runs,
+    // returns None if the instruction is going to fail
+    pub(crate) fn prepare(vm: &IntCodeVM<W>) -> Option<Self> {
+        let ptr = vm.ptr();
+        let instruction = Instruction::from_word(&vm.read_int(ptr).ok()?);
+        let code = instruction.code();
+        let count = code.parameter_count();
+        let reads = if code.writes_result() {
+            count - 1
+        } else {
+            count
+        };
+
+        let operands = (0..reads)
+            .map(|i| {
+                vm.read_parameter(ptr + 1 + i, instruction.get_param_mode(i))
+                    .ok()
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let mut writes = Vec::new();
+
+        if code.writes_result() {
+            let address = vm
+                .write_address(ptr + count, instruction.get_param_mode(count - 1))
+                .ok()?;
+            let old = vm.read_int(address).ok()?;
+
+            writes.push(MemoryWrite {
+                address,
+                new: old.clone(),
+                old,
+            });
+        }
+
+        Some(Step {
+            ptr,
+            instruction,
+            operands,
+            writes,
+            next_ptr: ptr,
+            relative_base: vm.relative_base().clone(),
+            output: None,
+        })
+    }
+
+    // complete the step with the state after the instruction ran
+    pub(crate) fn finish(mut self, vm: &IntCodeVM<W>, output: Option<W>) -> Self {
+        for write in &mut self.writes {
+            if let Ok(value) = vm.read_int(write.address) {
+                write.new = value;
+            }
+        }
+
+        self.next_ptr = vm.ptr();
+        self.relative_base = vm.relative_base().clone();
+        self.output = output;
+
+        self
+    }
+
+    /// The value consumed by an input instruction.
+    pub fn input(&self) -> Option<&W> {
+        match self.instruction.code() {
+            OpCode::Input => self.writes.first().map(|write| &write.new),
+            _ => None,
+        }
+    }
+
+    /// Apply the effects of the step to the given VM.
+    pub fn apply(&self, vm: &mut IntCodeVM<W>) -> Result<(), IntCodeError> {
+        for write in &self.writes {
+            vm.set_int(write.address, write.new.clone())?;
+        }
+
+        vm.jmp(self.next_ptr);
+        vm.set_relative_base(self.relative_base.clone());
+
+        if self.instruction.code() == OpCode::Exit {
+            vm.set_halted(true);
+        }
+
+        Ok(())
+    }
+}
+
+impl<W: Word> Display for Step<W> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}: {}", self.ptr, self.instruction.code().mnemonic())?;
+
+        for (i, operand) in self.operands.iter().enumerate() {
+            write!(f, "{}{}", if i == 0 { " " } else { ", " }, operand)?;
+        }
+
+        for write in &self.writes {
+            write!(f, " -> [{}] {} => {}", write.address, write.old, write.new)?;
+        }
+
+        if let Some(value) = &self.output {
+            write!(f, " => output {}", value)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Records all steps of a run.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Trace<W> {
+    pub steps: Vec<Step<W>>,
+}
+
+impl<W> Tracer<W> for Trace<W> {
+    fn trace(&mut self, step: Step<W>) {
+        self.steps.push(step)
+    }
+}
+
+impl<W: Word> Trace<W> {
+    pub fn new() -> Self {
+        Trace { steps: Vec::new() }
+    }
+
+    /// Reconstruct the state of the VM the trace was recorded on after the first
+    /// `steps` instructions, given its state before the first instruction.
+    pub fn replay(
+        &self,
+        initial: &IntCodeVM<W>,
+        steps: usize,
+    ) -> Result<IntCodeVM<W>, IntCodeError> {
+        let mut vm = initial.clone();
+
+        for step in self.steps.iter().take(steps) {
+            step.apply(&mut vm)?;
+        }
+
+        Ok(vm)
+    }
+
+    /// Index of the first step in which the two traces differ. If one trace is a
+    /// prefix of the other this is the length of the shorter one.
+    pub fn first_divergence(&self, other: &Self) -> Option<usize> {
+        self.steps
+            .iter()
+            .zip(&other.steps)
+            .position(|(a, b)| a != b)
+            .or_else(|| {
+                if self.steps.len() == other.steps.len() {
+                    None
+                } else {
+                    Some(self.steps.len().min(other.steps.len()))
+                }
+            })
+    }
+
+    /// Write one JSON object per step, e.g.
+    /// `{"ptr":0,"instruction":1101,"operands":[5,6],"writes":[[12,0,11]],"next":4,"base":0,"output":null}`
+    pub fn write_json_lines<O: Write>(&self, mut out: O) -> io::Result<()> {
+        for step in &self.steps {
+            let operands = step
+                .operands
+                .iter()
+                .map(|operand| operand.to_string())
+                .collect::<Vec<_>>();
+            let writes = step
+                .writes
+                .iter()
+                .map(|write| format!("[{},{},{}]", write.address, write.old, write.new))
+                .collect::<Vec<_>>();
+            let output = match &step.output {
+                Some(value) => value.to_string(),
+                None => "null".to_string(),
+            };
+
+            writeln!(
+                out,
+                r#"{{"ptr":{},"instruction":{},"operands":[{}],"writes":[{}],"next":{},"base":{},"output":{}}}"#,
+                step.ptr,
+                step.instruction.to_int(),
+                operands.join(","),
+                writes.join(","),
+                step.next_ptr,
+                step.relative_base,
+                output
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Read a trace written by `write_json_lines`. Empty lines are skipped.
+    pub fn read_json_lines<R: BufRead>(input: R) -> io::Result<Self> {
+        let mut steps = Vec::new();
+
+        for (index, line) in input.lines().enumerate() {
+            let line = line?;
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let step = parse_json_step(&line).ok_or_else(|| {
+                invalid_data(format!("line {}: invalid trace step {}", index + 1, line))
+            })?;
+            steps.push(step);
+        }
+
+        Ok(Trace { steps })
+    }
+
+    /// Write the trace in a compact binary format. Integers are stored as LEB128
+    /// varints, words are zigzag encoded and must fit into i64.
+    pub fn write_binary<O: Write>(&self, mut out: O) -> io::Result<()> {
+        out.write_all(BINARY_HEADER)?;
+        write_varint(&mut out, self.steps.len() as u64)?;
+
+        for step in &self.steps {
+            write_varint(&mut out, step.ptr as u64)?;
+            write_varint(&mut out, u64::from(step.instruction.to_int()))?;
+            write_varint(&mut out, step.operands.len() as u64)?;
+
+            for operand in &step.operands {
+                write_word(&mut out, operand)?;
+            }
+
+            write_varint(&mut out, step.writes.len() as u64)?;
+
+            for write in &step.writes {
+                write_varint(&mut out, write.address as u64)?;
+                write_word(&mut out, &write.old)?;
+                write_word(&mut out, &write.new)?;
+            }
+
+            write_varint(&mut out, step.next_ptr as u64)?;
+            write_word(&mut out, &step.relative_base)?;
+
+            match &step.output {
+                Some(value) => {
+                    out.write_all(&[1])?;
+                    write_word(&mut out, value)?;
+                }
+                None => out.write_all(&[0])?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read a trace written by `write_binary`.
+    pub fn read_binary<R: Read>(mut input: R) -> io::Result<Self> {
+        let mut header = [0; 5];
+        input.read_exact(&mut header)?;
+
+        if &header != BINARY_HEADER {
+            return Err(invalid_data("not a binary IntCode trace".to_string()));
+        }
+
+        let count = read_varint(&mut input)?;
+        let mut steps = Vec::new();
+
+        for _ in 0..count {
+            let ptr = read_usize(&mut input)?;
+            let instruction = read_varint(&mut input)?;
+            let instruction = std::convert::TryFrom::try_from(instruction)
+                .map(Instruction::from_int)
+                .map_err(|_| invalid_data(format!("invalid instruction {}", instruction)))?;
+
+            let operands = (0..read_varint(&mut input)?)
+                .map(|_| read_word(&mut input))
+                .collect::<io::Result<Vec<_>>>()?;
+
+            let writes = (0..read_varint(&mut input)?)
+                .map(|_| {
+                    Ok(MemoryWrite {
+                        address: read_usize(&mut input)?,
+                        old: read_word(&mut input)?,
+                        new: read_word(&mut input)?,
+                    })
+                })
+                .collect::<io::Result<Vec<_>>>()?;
+
+            let next_ptr = read_usize(&mut input)?;
+            let relative_base = read_word(&mut input)?;
+
+            let mut flag = [0];
+            input.read_exact(&mut flag)?;
+            let output = match flag[0] {
+                0 => None,
+                1 => Some(read_word(&mut input)?),
+                n => return Err(invalid_data(format!("invalid output flag {}", n))),
+            };
+
+            steps.push(Step {
+                ptr,
+                instruction,
+                operands,
+                writes,
+                next_ptr,
+                relative_base,
+                output,
+            });
+        }
+
+        Ok(Trace { steps })
+    }
+}
+
+/// Undo log of the instructions executed by a VM, used to step backwards. Only the
+/// most recent steps up to the limit are kept, older ones are forgotten.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct History<W> {
+    // relative base before the first kept step
+    relative_base: W,
+    steps: VecDeque<Step<W>>,
+    limit: usize,
+    forgotten: usize,
+}
+
+impl<W> Tracer<W> for History<W> {
+    fn trace(&mut self, step: Step<W>) {
+        self.steps.push_back(step);
+
+        if self.steps.len() > self.limit {
+            let oldest = self.steps.pop_front().unwrap();
+            self.relative_base = oldest.relative_base;
+            self.forgotten += 1;
+        }
+    }
+}
+
+impl<W: Word> History<W> {
+    /// Start an empty history at the current state of the VM, keeping up to
+    /// `DEFAULT_HISTORY_LIMIT` steps.
+    pub fn new(vm: &IntCodeVM<W>) -> Self {
+        History {
+            relative_base: vm.relative_base().clone(),
+            steps: VecDeque::new(),
+            limit: DEFAULT_HISTORY_LIMIT,
+            forgotten: 0,
+        }
+    }
+
+    /// Keep only the last `limit` steps.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// The kept steps, oldest first.
+    pub fn steps(&self) -> &VecDeque<Step<W>> {
+        &self.steps
+    }
+
+    /// Number of kept steps.
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Number of steps that were dropped to stay within the limit. The first kept
+    /// step is step number `forgotten() + 1`.
+    pub fn forgotten(&self) -> usize {
+        self.forgotten
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Number of steps traced since the history was started, including forgotten ones.
+    pub fn executed(&self) -> usize {
+        self.forgotten + self.steps.len()
+    }
+
+    /// Revert the VM to the state before the last recorded step and remove the step
+    /// from the history. Values consumed or emitted by the step are up to the caller,
+    /// see `Step::input` and `Step::output`.
+    pub fn undo(&mut self, vm: &mut IntCodeVM<W>) -> Result<Option<Step<W>>, IntCodeError> {
+        let step = match self.steps.back() {
+            Some(step) => step,
+            None => return Ok(None),
+        };
+
+        for write in step.writes.iter().rev() {
+            vm.set_int(write.address, write.old.clone())?;
+        }
+
+        vm.jmp(step.ptr);
+        vm.set_halted(false);
+
+        let step = self.steps.pop_back().unwrap();
+        let relative_base = self
+            .steps
+            .back()
+            .map_or(&self.relative_base, |previous| &previous.relative_base);
+        vm.set_relative_base(relative_base.clone());
+
+        Ok(Some(step))
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn write_varint<O: Write>(out: &mut O, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            return out.write_all(&[byte]);
+        }
+
+        out.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_varint<R: Read>(input: &mut R) -> io::Result<u64> {
+    let mut value = 0;
+
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+        input.read_exact(&mut byte)?;
+        value |= u64::from(byte[0] & 0x7f) << shift;
+
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(invalid_data("varint is too long".to_string()))
+}
+
+fn read_usize<R: Read>(input: &mut R) -> io::Result<usize> {
+    let value = read_varint(input)?;
+
+    std::convert::TryFrom::try_from(value)
+        .map_err(|_| invalid_data(format!("address {} is too large", value)))
+}
+
+fn write_word<O: Write, W: Word>(out: &mut O, word: &W) -> io::Result<()> {
+    let value = word
+        .to_i64()
+        .ok_or_else(|| invalid_data(format!("{} doesn't fit into 64 bits", word)))?;
+
+    write_varint(out, ((value << 1) ^ (value >> 63)) as u64)
+}
+
+fn read_word<R: Read, W: Word>(input: &mut R) -> io::Result<W> {
+    let value = read_varint(input)?;
+    let value = ((value >> 1) as i64) ^ -((value & 1) as i64);
+
+    W::from_i64(value).ok_or_else(|| invalid_data(format!("{} doesn't fit into a word", value)))
+}
+
+/// The subset of JSON written by `write_json_lines`.
+#[derive(Debug, PartialEq)]
+enum Json {
+    Null,
+    Number(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    fn parse<T: std::str::FromStr>(&self) -> Option<T> {
+        match self {
+            Json::Number(number) => number.parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+fn parse_json_step<W: Word>(line: &str) -> Option<Step<W>> {
+    let mut chars = line.trim().chars().peekable();
+    let json = parse_json(&mut chars)?;
+
+    if chars.next().is_some() {
+        return None;
+    }
+
+    let operands = json
+        .get("operands")?
+        .array()?
+        .iter()
+        .map(Json::parse)
+        .collect::<Option<Vec<_>>>()?;
+
+    let writes = json
+        .get("writes")?
+        .array()?
+        .iter()
+        .map(|write| match write.array()? {
+            [address, old, new] => Some(MemoryWrite {
+                address: address.parse()?,
+                old: old.parse()?,
+                new: new.parse()?,
+            }),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let output = match json.get("output")? {
+        Json::Null => None,
+        value => Some(value.parse()?),
+    };
+
+    Some(Step {
+        ptr: json.get("ptr")?.parse()?,
+        instruction: Instruction::from_int(json.get("instruction")?.parse()?),
+        operands,
+        writes,
+        next_ptr: json.get("next")?.parse()?,
+        relative_base: json.get("base")?.parse()?,
+        output,
+    })
+}
+
+fn parse_json(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Option<Json> {
+    skip_whitespace(chars);
+
+    match *chars.peek()? {
+        '{' => {
+            chars.next();
+            let mut fields = Vec::new();
+
+            loop {
+                skip_whitespace(chars);
+
+                match chars.next()? {
+                    '}' if fields.is_empty() => return Some(Json::Object(fields)),
+                    '"' => {}
+                    _ => return None,
+                }
+
+                let key = chars.by_ref().take_while(|&c| c != '"').collect();
+                skip_whitespace(chars);
+
+                if chars.next()? != ':' {
+                    return None;
+                }
+
+                fields.push((key, parse_json(chars)?));
+                skip_whitespace(chars);
+
+                match chars.next()? {
+                    ',' => {}
+                    '}' => return Some(Json::Object(fields)),
+                    _ => return None,
+                }
+            }
+        }
+        '[' => {
+            chars.next();
+            let mut values = Vec::new();
+
+            loop {
+                skip_whitespace(chars);
+
+                if values.is_empty() && chars.peek() == Some(&']') {
+                    chars.next();
+                    return Some(Json::Array(values));
+                }
+
+                values.push(parse_json(chars)?);
+                skip_whitespace(chars);
+
+                match chars.next()? {
+                    ',' => {}
+                    ']' => return Some(Json::Array(values)),
+                    _ => return None,
+                }
+            }
+        }
+        'n' => {
+            let word = chars.by_ref().take(4).collect::<String>();
+
+            if word == "null" {
+                Some(Json::Null)
+            } else {
+                None
+            }
+        }
+        c if c == '-' || c.is_ascii_digit() => {
+            let mut number = String::new();
+
+            while let Some(&c) = chars.peek() {
+                if c != '-' && !c.is_ascii_digit() {
+                    break;
+                }
+
+                number.push(c);
+                chars.next();
+            }
+
+            Some(Json::Number(number))
+        }
+        _ => None,
+    }
+}
+
+fn skip_whitespace(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) {
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::intcode::trace::*;
+
+    use std::collections::VecDeque;
+
+    // double the input until it exceeds 100, then print it
+    const PROGRAM: &str = "3,16,1002,16,2,16,1007,16,100,17,1005,17,2,4,16,99,0,0";
+
+    fn record(input: i64) -> (IntCodeVM, Trace<i64>) {
+        let initial: IntCodeVM = PROGRAM.parse().unwrap();
+        let mut trace = Trace::new();
+        initial
+            .clone()
+            .run_traced(&mut VecDeque::from(vec![input]), &mut (), &mut trace)
+            .unwrap();
+
+        (initial, trace)
+    }
+
+    #[test]
+    fn test_trace_steps() {
+        let (_, trace) = record(7);
+
+        assert_eq!(
+            Step {
+                ptr: 0,
+                instruction: Instruction::from_int(3),
+                operands: vec![],
+                writes: vec![MemoryWrite {
+                    address: 16,
+                    old: 0,
+                    new: 7
+                }],
+                next_ptr: 2,
+                relative_base: 0,
+                output: None,
+            },
+            trace.steps[0]
+        );
+        assert_eq!("0002: MUL 7, 2 -> [16] 7 => 14", trace.steps[1].to_string());
+        assert_eq!(
+            "0013: OUT 112 => output 112",
+            trace.steps[trace.steps.len() - 2].to_string()
+        );
+        assert_eq!(OpCode::Exit, trace.steps.last().unwrap().instruction.code());
+    }
+
+    #[test]
+    fn test_trace_replay() {
+        let (initial, trace) = record(7);
+
+        for steps in 0..=trace.steps.len() {
+            let mut expected = initial.clone();
+            let mut input = VecDeque::from(vec![7]);
+
+            for _ in 0..steps {
+                expected.run_instruction(&mut input, &mut ()).unwrap();
+            }
+
+            assert_eq!(expected, trace.replay(&initial, steps).unwrap());
+        }
+
+        assert!(trace
+            .replay(&initial, trace.steps.len())
+            .unwrap()
+            .is_halted());
+    }
+
+    #[test]
+    fn test_history_undo() {
+        let mut vm: IntCodeVM = "109,5,203,2,99,0,0,0".parse().unwrap();
+        let mut history = History::new(&vm);
+        let mut states = vec![vm.clone()];
+
+        vm.run_traced(&mut VecDeque::from(vec![42]), &mut (), &mut history)
+            .unwrap();
+        assert_eq!(3, history.len());
+        assert_eq!(Some(&42), history.steps()[1].input());
+
+        while let Some(step) = history.undo(&mut vm).unwrap() {
+            states.push(vm.clone());
+            assert_eq!(step.ptr, vm.ptr());
+        }
+
+        let mut forward = states[0].clone();
+        let mut input = VecDeque::from(vec![42]);
+
+        for expected in states.iter().skip(1).rev() {
+            assert_eq!(&forward, expected);
+            forward.run_instruction(&mut input, &mut ()).unwrap();
+        }
+
+        assert_eq!(vm, states[0]);
+        assert_eq!(None, history.undo(&mut vm).unwrap());
+        assert_eq!(0, history.forgotten());
+    }
+
+    #[test]
+    fn test_history_limit() {
+        let mut vm: IntCodeVM = "109,5,203,2,99,0,0,0".parse().unwrap();
+        let mut history = History::new(&vm).with_limit(2);
+        let mut after_first = vm.clone();
+        after_first.run_instruction(&mut (), &mut ()).unwrap();
+
+        vm.run_traced(&mut VecDeque::from(vec![42]), &mut (), &mut history)
+            .unwrap();
+        assert_eq!(2, history.len());
+        assert_eq!(1, history.forgotten());
+        assert_eq!(3, history.executed());
+        assert_eq!(Some(&42), history.steps()[0].input());
+
+        assert!(history.undo(&mut vm).unwrap().is_some());
+        assert!(history.undo(&mut vm).unwrap().is_some());
+        assert_eq!(None, history.undo(&mut vm).unwrap());
+        assert_eq!(after_first, vm);
+    }
+
+    #[test]
+    fn test_trace_json_lines() {
+        let (_, trace) = record(7);
+        let mut json = Vec::new();
+        trace.write_json_lines(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+
+        assert_eq!(
+            r#"{"ptr":0,"instruction":3,"operands":[],"writes":[[16,0,7]],"next":2,"base":0,"output":null}"#,
+            json.lines().next().unwrap()
+        );
+        assert_eq!(trace, Trace::read_json_lines(json.as_bytes()).unwrap());
+        assert!(Trace::<i64>::read_json_lines("{\"ptr\":0}".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_trace_binary() {
+        let (_, trace) = record(99);
+        let mut binary = Vec::new();
+        trace.write_binary(&mut binary).unwrap();
+
+        assert_eq!(trace, Trace::read_binary(binary.as_slice()).unwrap());
+        assert!(Trace::<i64>::read_binary(&b"ICTR\x02"[..]).is_err());
+        assert!(Trace::<i64>::read_binary(&binary[..binary.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_trace_divergence() {
+        let (_, a) = record(7);
+        let (_, b) = record(8);
+        let (_, c) = record(13);
+
+        assert_eq!(None, a.first_divergence(&a));
+        assert_eq!(Some(0), a.first_divergence(&b));
+        assert_eq!(
+            Some(5),
+            c.first_divergence(&Trace {
+                steps: c.steps[..5].to_vec()
+            })
+        );
+    }
+}
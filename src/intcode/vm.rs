@@ -0,0 +1,691 @@
+use crate::intcode::cache::DecodeCache;
+use crate::intcode::trace::{Step, Tracer};
+use crate::intcode::{
+    Budget, Input, Instruction, IntCodeError, Memory, OpCode, Output, OutputFn, ParamMode, Word,
+};
+
+use std::collections::VecDeque;
+
+/// Why the VM stopped when running cooperatively, see `IntCodeVM::resume`.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Status<W> {
+    /// An input instruction was reached but no input is pending.
+    NeedsInput,
+    /// The program emitted a value.
+    Output(W),
+    /// An exit instruction was reached.
+    Halted,
+}
+
+/// The VM is generic over the type of its memory cells. Arithmetic on fixed-size
+/// words is checked and fails with `IntCodeError::ArithmeticOverflow`.
+#[derive(Debug, Clone)]
+pub struct IntCodeVM<W: Word = i64> {
+    memory: Memory<W>,
+    ptr: usize,
+    relative_base: W,
+    input: VecDeque<W>,
+    halted: bool,
+    cache: DecodeCache,
+}
+
+/// The decode cache is not part of the state of the VM.
+impl<W: Word> PartialEq for IntCodeVM<W> {
+    fn eq(&self, other: &Self) -> bool {
+        self.memory == other.memory
+            && self.ptr == other.ptr
+            && self.relative_base == other.relative_base
+            && self.input == other.input
+            && self.halted == other.halted
+    }
+}
+
+impl<W: Word + Eq> Eq for IntCodeVM<W> {}
+
+impl<W: Word> IntCodeVM<W> {
+    pub fn new(memory: Vec<W>) -> Self {
+        Self::with_memory(Memory::dense(memory))
+    }
+
+    /// Create a VM on top of preconfigured memory, e.g. `Memory::paged`.
+    pub fn with_memory(memory: Memory<W>) -> Self {
+        Self {
+            memory,
+            ptr: 0,
+            relative_base: W::zero(),
+            input: VecDeque::new(),
+            halted: false,
+            cache: DecodeCache::new(),
+        }
+    }
+
+    /// Enable or disable caching of decoded instructions, enabled by default. A write
+    /// only evicts the instruction decoded from the written cell, and parameters are
+    /// always read from memory, so self-modifying programs run correctly either way.
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.cache.set_enabled(enabled)
+    }
+
+    /// Run the next instruction and increment the instruction pointer.
+    /// Returns false if an exit instruction (code 99) was reached.
+    pub fn run_instruction(
+        &mut self,
+        input: &mut dyn Input<W>,
+        output: &mut dyn Output<W>,
+    ) -> Result<bool, IntCodeError> {
+        let instruction = match self.cache.get(&self.memory, self.ptr) {
+            Some(instruction) => instruction,
+            None => return Err(self.out_of_bounds(self.ptr)),
+        };
+
+        match instruction.code() {
+            OpCode::Add => {
+                let lhs = self.read_parameter(self.ptr + 1, instruction.get_param_mode(0))?;
+                let rhs = self.read_parameter(self.ptr + 2, instruction.get_param_mode(1))?;
+                let res = self.write_address(self.ptr + 3, instruction.get_param_mode(2))?;
+                let sum = lhs
+                    .checked_add(&rhs)
+                    .ok_or_else(|| self.arithmetic_overflow())?;
+
+                self.set_int(res, sum)?;
+                self.jmp(self.ptr + 4);
+
+                Ok(true)
+            }
+            OpCode::Multiply => {
+                let lhs = self.read_parameter(self.ptr + 1, instruction.get_param_mode(0))?;
+                let rhs = self.read_parameter(self.ptr + 2, instruction.get_param_mode(1))?;
+                let res = self.write_address(self.ptr + 3, instruction.get_param_mode(2))?;
+                let product = lhs
+                    .checked_mul(&rhs)
+                    .ok_or_else(|| self.arithmetic_overflow())?;
+
+                self.set_int(res, product)?;
+                self.jmp(self.ptr + 4);
+
+                Ok(true)
+            }
+            OpCode::Input => {
+                let dst = self.write_address(self.ptr + 1, instruction.get_param_mode(0))?;
+                let value = input.read().ok_or(IntCodeError::InputExhausted {
+                    ptr: self.ptr,
+                    instruction,
+                })?;
+                self.set_int(dst, value)?;
+                self.jmp(self.ptr + 2);
+
+                Ok(true)
+            }
+            OpCode::Output => {
+                let value = self.read_parameter(self.ptr + 1, instruction.get_param_mode(0))?;
+                output.write(value);
+                self.jmp(self.ptr + 2);
+
+                Ok(true)
+            }
+            OpCode::JumpIfTrue => {
+                let value = self.read_parameter(self.ptr + 1, instruction.get_param_mode(0))?;
+                let dst = self
+                    .address(&self.read_parameter(self.ptr + 2, instruction.get_param_mode(1))?)?;
+
+                if value != W::zero() {
+                    self.jmp(dst);
+                } else {
+                    self.jmp(self.ptr + 3);
+                }
+
+                Ok(true)
+            }
+            OpCode::JumpIfFalse => {
+                let value = self.read_parameter(self.ptr + 1, instruction.get_param_mode(0))?;
+                let dst = self
+                    .address(&self.read_parameter(self.ptr + 2, instruction.get_param_mode(1))?)?;
+
+                if value == W::zero() {
+                    self.jmp(dst);
+                } else {
+                    self.jmp(self.ptr + 3);
+                }
+
+                Ok(true)
+            }
+            OpCode::LessThan => {
+                let lhs = self.read_parameter(self.ptr + 1, instruction.get_param_mode(0))?;
+                let rhs = self.read_parameter(self.ptr + 2, instruction.get_param_mode(1))?;
+                let dst = self.write_address(self.ptr + 3, instruction.get_param_mode(2))?;
+
+                self.set_int(dst, if lhs < rhs { W::one() } else { W::zero() })?;
+
+                self.jmp(self.ptr + 4);
+
+                Ok(true)
+            }
+            OpCode::Equals => {
+                let lhs = self.read_parameter(self.ptr + 1, instruction.get_param_mode(0))?;
+                let rhs = self.read_parameter(self.ptr + 2, instruction.get_param_mode(1))?;
+                let dst = self.write_address(self.ptr + 3, instruction.get_param_mode(2))?;
+
+                self.set_int(dst, if lhs == rhs { W::one() } else { W::zero() })?;
+
+                self.jmp(self.ptr + 4);
+
+                Ok(true)
+            }
+            OpCode::AdjustRelativeBase => {
+                let offset = self.read_parameter(self.ptr + 1, instruction.get_param_mode(0))?;
+                self.relative_base = self
+                    .relative_base
+                    .checked_add(&offset)
+                    .ok_or_else(|| self.arithmetic_overflow())?;
+                self.jmp(self.ptr + 2);
+
+                Ok(true)
+            }
+            OpCode::Exit => {
+                self.halted = true;
+
+                Ok(false)
+            }
+            OpCode::Invalid(_) => Err(IntCodeError::InvalidOpCode {
+                ptr: self.ptr,
+                instruction,
+            }),
+        }
+    }
+
+    /// Run until an exit instruction is reached, reading from `input` and writing to `output`.
+    pub fn run(
+        &mut self,
+        input: &mut dyn Input<W>,
+        output: &mut dyn Output<W>,
+    ) -> Result<(), IntCodeError> {
+        while self.run_instruction(input, output)? {}
+
+        Ok(())
+    }
+
+    /// Like `run`, but fails with `IntCodeError::StepLimitExceeded` if the program
+    /// doesn't halt within `limit` instructions.
+    pub fn run_limited(
+        &mut self,
+        input: &mut dyn Input<W>,
+        output: &mut dyn Output<W>,
+        limit: u64,
+    ) -> Result<(), IntCodeError> {
+        self.run_with_budget(input, output, &Budget::default().with_max_steps(limit))
+    }
+
+    /// Like `run_instruction`, but reports the executed instruction to `tracer`.
+    /// Instructions that fail are not reported.
+    pub fn run_instruction_traced(
+        &mut self,
+        input: &mut dyn Input<W>,
+        output: &mut dyn Output<W>,
+        tracer: &mut dyn Tracer<W>,
+    ) -> Result<bool, IntCodeError> {
+        let step = Step::prepare(self);
+        let mut emitted = None;
+        let running = self.run_instruction(
+            input,
+            &mut OutputFn(|value: W| {
+                emitted = Some(value.clone());
+                output.write(value)
+            }),
+        )?;
+
+        if let Some(step) = step {
+            tracer.trace(step.finish(self, emitted));
+        }
+
+        Ok(running)
+    }
+
+    /// Like `run`, but reports every executed instruction to `tracer`.
+    pub fn run_traced(
+        &mut self,
+        input: &mut dyn Input<W>,
+        output: &mut dyn Output<W>,
+        tracer: &mut dyn Tracer<W>,
+    ) -> Result<(), IntCodeError> {
+        while self.run_instruction_traced(input, output, tracer)? {}
+
+        Ok(())
+    }
+
+    /// Queue a value for the next input instruction executed by `resume`.
+    pub fn push_input(&mut self, value: W) {
+        self.input.push_back(value)
+    }
+
+    /// Values queued by `push_input` that were not consumed yet.
+    pub fn pending_input(&self) -> &VecDeque<W> {
+        &self.input
+    }
+
+    /// Run until the program emits a value, needs input that has not been pushed yet
+    /// or halts. The VM stops in front of the pending input instruction, so calling
+    /// `resume` again after `push_input` continues exactly where it stopped.
+    pub fn resume(&mut self) -> Result<Status<W>, IntCodeError> {
+        self.resume_with(None)
+            .map(|status| status.expect("resume without a limit always stops with a status"))
+    }
+
+    /// Like `resume`, but executes at most `steps` instructions. Returns None if the
+    /// VM stopped because it reached that limit, calling it again continues.
+    pub fn resume_limited(&mut self, steps: u64) -> Result<Option<Status<W>>, IntCodeError> {
+        self.resume_with(Some(steps))
+    }
+
+    fn resume_with(&mut self, limit: Option<u64>) -> Result<Option<Status<W>>, IntCodeError> {
+        let mut input = std::mem::take(&mut self.input);
+        let status = self.run_until_stopped(&mut input, limit);
+        self.input = input;
+
+        status
+    }
+
+    fn run_until_stopped(
+        &mut self,
+        input: &mut VecDeque<W>,
+        limit: Option<u64>,
+    ) -> Result<Option<Status<W>>, IntCodeError> {
+        let mut steps = 0;
+
+        while !self.halted {
+            if limit == Some(steps) {
+                return Ok(None);
+            }
+
+            let mut emitted = None;
+            steps += 1;
+
+            match self.run_instruction(input, &mut OutputFn(|value| emitted = Some(value))) {
+                Ok(_) => {
+                    if let Some(value) = emitted {
+                        return Ok(Some(Status::Output(value)));
+                    }
+                }
+                Err(IntCodeError::InputExhausted { .. }) => return Ok(Some(Status::NeedsInput)),
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(Some(Status::Halted))
+    }
+
+    /// Returns true once an exit instruction was reached.
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// The instruction pointer.
+    pub fn ptr(&self) -> usize {
+        self.ptr
+    }
+
+    pub fn relative_base(&self) -> &W {
+        &self.relative_base
+    }
+
+    pub fn set_relative_base(&mut self, base: W) {
+        self.relative_base = base
+    }
+
+    /// Mark the VM as halted, e.g. when restoring a recorded state.
+    pub fn set_halted(&mut self, halted: bool) {
+        self.halted = halted
+    }
+
+    pub fn memory(&self) -> &Memory<W> {
+        &self.memory
+    }
+
+    // read the value at the given position in memory
+    pub fn read_int(&self, position: usize) -> Result<W, IntCodeError> {
+        self.memory
+            .get(position)
+            .ok_or_else(|| self.out_of_bounds(position))
+    }
+
+    // store the value at the given position in memory
+    pub fn set_int(&mut self, position: usize, value: W) -> Result<(), IntCodeError> {
+        if self.memory.set(position, value) {
+            self.cache.invalidate(position);
+            Ok(())
+        } else {
+            Err(self.out_of_bounds(position))
+        }
+    }
+
+    pub fn read_parameter(&self, position: usize, mode: ParamMode) -> Result<W, IntCodeError> {
+        match mode {
+            ParamMode::Positional => self.read_int(self.address(&self.read_int(position)?)?),
+            ParamMode::Immediate => self.read_int(position),
+            ParamMode::Relative => self.read_int(self.relative_address(position)?),
+            ParamMode::Invalid(_) => Err(IntCodeError::InvalidParameterMode {
+                ptr: self.ptr,
+                instruction: self.current_instruction(),
+                parameter: position.saturating_sub(self.ptr + 1),
+            }),
+        }
+    }
+
+    /// Resolve the memory address a parameter writes to. Write parameters never
+    /// use immediate mode.
+    pub fn write_address(&self, position: usize, mode: ParamMode) -> Result<usize, IntCodeError> {
+        match mode {
+            ParamMode::Positional => self.address(&self.read_int(position)?),
+            ParamMode::Relative => self.relative_address(position),
+            ParamMode::Immediate => Err(IntCodeError::ImmediateWrite {
+                ptr: self.ptr,
+                instruction: self.current_instruction(),
+                parameter: position.saturating_sub(self.ptr + 1),
+            }),
+            ParamMode::Invalid(_) => Err(IntCodeError::InvalidParameterMode {
+                ptr: self.ptr,
+                instruction: self.current_instruction(),
+                parameter: position.saturating_sub(self.ptr + 1),
+            }),
+        }
+    }
+
+    // resolve the parameter at the given position relative to the relative base
+    fn relative_address(&self, position: usize) -> Result<usize, IntCodeError> {
+        let offset = self.read_int(position)?;
+        let target = self
+            .relative_base
+            .checked_add(&offset)
+            .ok_or_else(|| self.arithmetic_overflow())?;
+
+        self.address(&target)
+    }
+
+    // interpret a word as memory address, addresses that don't fit into usize are
+    // beyond any memory limit
+    fn address(&self, value: &W) -> Result<usize, IntCodeError> {
+        if *value < W::zero() {
+            return Err(IntCodeError::NegativeAddress {
+                ptr: self.ptr,
+                instruction: self.current_instruction(),
+                address: value.to_string(),
+            });
+        }
+
+        Ok(value
+            .to_i64()
+            .and_then(|i| std::convert::TryFrom::try_from(i).ok())
+            .unwrap_or(usize::MAX))
+    }
+
+    /// The instruction at the instruction pointer.
+    pub fn current_instruction(&self) -> Instruction {
+        Instruction::from_word(&self.memory.get(self.ptr).unwrap_or_else(W::zero))
+    }
+
+    fn out_of_bounds(&self, address: usize) -> IntCodeError {
+        IntCodeError::OutOfBounds {
+            ptr: self.ptr,
+            instruction: self.current_instruction(),
+            address,
+        }
+    }
+
+    fn arithmetic_overflow(&self) -> IntCodeError {
+        IntCodeError::ArithmeticOverflow {
+            ptr: self.ptr,
+            instruction: self.current_instruction(),
+        }
+    }
+
+    /// set the instruction pointer to the given position
+    pub fn jmp(&mut self, destination: usize) {
+        self.ptr = destination
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::intcode::vm::*;
+    use crate::intcode::InputFn;
+
+    #[test]
+    fn test_vm_from_str() {
+        let input = "1,2,3,11,1337,99";
+        let vm = IntCodeVM::new(vec![1, 2, 3, 11, 1337, 99]);
+
+        assert_eq!(vm, input.parse().unwrap())
+    }
+
+    #[test]
+    fn test_vm_get_int() {
+        let vm = IntCodeVM::new(vec![1, 2, 3, 11, 1337, 99]);
+
+        assert_eq!(Ok(1337), vm.read_int(4));
+    }
+
+    #[test]
+    fn test_vm_set_int() {
+        let mut vm = IntCodeVM::new(vec![1, 2, 3, 11, 1337, 99]);
+        vm.set_int(4, 11).unwrap();
+
+        assert_eq!(Ok(11), vm.read_int(4));
+    }
+
+    #[test]
+    fn test_vm_jump() {
+        let mut vm: IntCodeVM = IntCodeVM::new(Vec::new());
+        vm.jmp(5);
+
+        assert_eq!(vm.ptr, 5);
+    }
+
+    #[test]
+    fn test_vm_read_parameter() {
+        let vm = IntCodeVM::new(vec![1, 4, 3, 11, 1337, 99]);
+        assert_eq!(vm.read_parameter(1, ParamMode::Positional).unwrap(), 1337);
+        assert_eq!(vm.read_parameter(1, ParamMode::Immediate).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_vm_input_output() {
+        let mut vm: IntCodeVM = "3,0,4,0,99".parse().unwrap();
+        let mut input = VecDeque::from(vec![42]);
+        let mut output = Vec::new();
+        vm.run(&mut input, &mut output).unwrap();
+
+        assert!(input.is_empty());
+        assert_eq!(vec![42], output);
+    }
+
+    #[test]
+    fn test_vm_missing_input() {
+        let mut vm: IntCodeVM = "3,0,4,0,99".parse().unwrap();
+        let err = vm.run(&mut VecDeque::new(), &mut Vec::new()).unwrap_err();
+
+        assert_eq!(
+            IntCodeError::InputExhausted {
+                ptr: 0,
+                instruction: Instruction::from_int(3)
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn test_vm_closure_io() {
+        let mut vm: IntCodeVM = "3,9,8,9,10,9,4,9,99,-1,8".parse().unwrap();
+        let mut outputs = Vec::new();
+        vm.run(
+            &mut InputFn(|| Some(8)),
+            &mut OutputFn(|value| outputs.push(value)),
+        )
+        .unwrap();
+
+        assert_eq!(vec![1], outputs);
+    }
+
+    #[test]
+    fn test_vm_resume() {
+        let mut vm: IntCodeVM = "3,11,1,11,11,12,4,12,4,11,99,0,0".parse().unwrap();
+        assert_eq!(Status::NeedsInput, vm.resume().unwrap());
+        assert_eq!(Status::NeedsInput, vm.resume().unwrap());
+        assert_eq!(0, vm.ptr);
+
+        vm.push_input(21);
+        assert_eq!(Status::Output(42), vm.resume().unwrap());
+        assert_eq!(Status::Output(21), vm.resume().unwrap());
+        assert_eq!(Status::Halted, vm.resume().unwrap());
+        assert_eq!(Status::Halted, vm.resume().unwrap());
+        assert!(vm.is_halted());
+
+        let mut vm: IntCodeVM = "3,11,1,11,11,12,4,12,4,11,99,0,0".parse().unwrap();
+        vm.push_input(21);
+        assert_eq!(None, vm.resume_limited(1).unwrap());
+        assert_eq!(2, vm.ptr);
+        assert_eq!(Some(Status::Output(42)), vm.resume_limited(2).unwrap());
+        assert_eq!(None, vm.resume_limited(0).unwrap());
+    }
+
+    #[test]
+    fn test_vm_word_sizes() {
+        let program = "1102,34915192,34915192,7,4,7,99,0";
+
+        let mut vm: IntCodeVM<i32> = program.parse().unwrap();
+        let err = vm.run(&mut VecDeque::new(), &mut Vec::new()).unwrap_err();
+        assert_eq!(
+            IntCodeError::ArithmeticOverflow {
+                ptr: 0,
+                instruction: Instruction::from_int(1102)
+            },
+            err
+        );
+
+        let mut vm: IntCodeVM<i64> = program.parse().unwrap();
+        let mut output = Vec::new();
+        vm.run(&mut VecDeque::new(), &mut output).unwrap();
+        assert_eq!(vec![1_219_070_632_396_864], output);
+
+        let mut vm: IntCodeVM<i128> = "1102,9223372036854775807,4,7,4,7,99,0".parse().unwrap();
+        let mut output = Vec::new();
+        vm.run(&mut VecDeque::new(), &mut output).unwrap();
+        assert_eq!(vec![i128::from(i64::MAX) * 4], output);
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn test_vm_bigint() {
+        use num_bigint::BigInt;
+
+        let mut vm: IntCodeVM<BigInt> = "1002,7,170141183460469231731687303715884105727,7,4,7,99,2"
+            .parse()
+            .unwrap();
+        let mut output = Vec::new();
+        vm.run(&mut VecDeque::new(), &mut output).unwrap();
+
+        assert_eq!(vec![BigInt::from(i128::MAX) * 2], output);
+    }
+
+    #[test]
+    fn test_vm_relative_mode() {
+        let mut vm: IntCodeVM = "109,11,204,-1,21101,3,4,1,204,1,99,0,0".parse().unwrap();
+        let mut output = Vec::new();
+        vm.run(&mut VecDeque::new(), &mut output).unwrap();
+
+        assert_eq!(vm.relative_base, 11);
+        assert_eq!(vec![99, 7], output);
+    }
+
+    #[test]
+    fn test_vm_write_in_immediate_mode() {
+        let mut vm: IntCodeVM = "11101,1,1,0,99".parse().unwrap();
+        let err = vm.run(&mut VecDeque::new(), &mut Vec::new()).unwrap_err();
+
+        assert_eq!(
+            IntCodeError::ImmediateWrite {
+                ptr: 0,
+                instruction: Instruction::from_int(11101),
+                parameter: 2
+            },
+            err
+        );
+    }
+
+    #[test]
+    fn test_vm_negative_address() {
+        let mut vm = IntCodeVM::with_memory(Memory::paged(vec![1101, 1, 1, -1, 99]));
+        let err = vm.run(&mut VecDeque::new(), &mut Vec::new()).unwrap_err();
+
+        assert_eq!(
+            IntCodeError::NegativeAddress {
+                ptr: 0,
+                instruction: Instruction::from_int(1101),
+                address: "-1".to_string()
+            },
+            err
+        );
+        assert_eq!(
+            "negative address -1 in instruction 1101 (ADD) at position 0",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_vm_invalid_instructions() {
+        let mut vm: IntCodeVM = "1101,1,1,5,42,0".parse().unwrap();
+        let err = vm.run(&mut VecDeque::new(), &mut Vec::new()).unwrap_err();
+
+        assert_eq!(
+            IntCodeError::InvalidOpCode {
+                ptr: 4,
+                instruction: Instruction::from_int(42)
+            },
+            err
+        );
+        assert_eq!(4, err.ptr());
+
+        let mut vm: IntCodeVM = "3001,1,1,5,99".parse().unwrap();
+        let err = vm.run(&mut VecDeque::new(), &mut Vec::new()).unwrap_err();
+
+        assert_eq!(
+            IntCodeError::InvalidParameterMode {
+                ptr: 0,
+                instruction: Instruction::from_int(3001),
+                parameter: 1
+            },
+            err
+        );
+        assert_eq!(
+            "invalid mode 3 of parameter 2 in instruction 3001 (ADD) at position 0",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_vm_step_limit() {
+        let mut vm: IntCodeVM = "1105,1,0".parse().unwrap();
+
+        assert_eq!(
+            Err(IntCodeError::StepLimitExceeded {
+                ptr: 0,
+                instruction: Instruction::from_int(1105),
+                limit: 100
+            }),
+            vm.run_limited(&mut (), &mut (), 100)
+        );
+
+        let mut vm: IntCodeVM = "1,0,0,0,99".parse().unwrap();
+        assert_eq!(Ok(()), vm.run_limited(&mut (), &mut (), 2));
+    }
+
+    #[test]
+    fn test_vm_channel_io() {
+        let (input_tx, mut input_rx) = std::sync::mpsc::channel();
+        let (mut output_tx, output_rx) = std::sync::mpsc::channel();
+        input_tx.send(7).unwrap();
+
+        let mut vm: IntCodeVM = "3,9,7,9,10,9,4,9,99,-1,8".parse().unwrap();
+        vm.run(&mut input_rx, &mut output_tx).unwrap();
+
+        assert_eq!(Ok(1), output_rx.recv());
+    }
+}
@@ -0,0 +1,84 @@
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use std::str::FromStr;
+
+/// A value stored in a memory cell of the VM.
+pub trait Word: Clone + Debug + Display + PartialEq + PartialOrd + FromStr + Hash {
+    fn zero() -> Self;
+    fn one() -> Self;
+
+    /// Returns None if the value can't be represented as i64.
+    fn to_i64(&self) -> Option<i64>;
+
+    /// Returns None if the value doesn't fit into the word.
+    fn from_i64(value: i64) -> Option<Self>;
+
+    /// Returns None if the result doesn't fit into the word.
+    fn checked_add(&self, rhs: &Self) -> Option<Self>;
+
+    /// Returns None if the result doesn't fit into the word.
+    fn checked_mul(&self, rhs: &Self) -> Option<Self>;
+}
+
+macro_rules! impl_word {
+    ($($t:ty),*) => {
+        $(
+            impl Word for $t {
+                fn zero() -> Self {
+                    0
+                }
+
+                fn one() -> Self {
+                    1
+                }
+
+                #[allow(clippy::unnecessary_fallible_conversions)]
+                fn to_i64(&self) -> Option<i64> {
+                    std::convert::TryFrom::try_from(*self).ok()
+                }
+
+                #[allow(clippy::unnecessary_fallible_conversions)]
+                fn from_i64(value: i64) -> Option<Self> {
+                    std::convert::TryFrom::try_from(value).ok()
+                }
+
+                fn checked_add(&self, rhs: &Self) -> Option<Self> {
+                    <$t>::checked_add(*self, *rhs)
+                }
+
+                fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+                    <$t>::checked_mul(*self, *rhs)
+                }
+            }
+        )*
+    };
+}
+
+impl_word!(i32, i64, i128);
+
+#[cfg(feature = "bigint")]
+impl Word for num_bigint::BigInt {
+    fn zero() -> Self {
+        num_traits::Zero::zero()
+    }
+
+    fn one() -> Self {
+        num_traits::One::one()
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        num_traits::ToPrimitive::to_i64(self)
+    }
+
+    fn from_i64(value: i64) -> Option<Self> {
+        Some(Self::from(value))
+    }
+
+    fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        Some(self + rhs)
+    }
+
+    fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        Some(self * rhs)
+    }
+}
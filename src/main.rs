@@ -1,3 +1,114 @@
+use advent_of_code_2019::intcode::ascii::AsciiVM;
+use advent_of_code_2019::intcode::cfg::ControlFlowGraph;
+use advent_of_code_2019::intcode::debugger::Debugger;
+use advent_of_code_2019::intcode::screen::Screen;
+use advent_of_code_2019::intcode::IntCodeVM;
 use aoc_runner_derive::aoc_main;
 
-aoc_main! { lib = advent_of_code_2019 }
+const USAGE: &str = "\
+usage: advent-of-code-2019                  run all solutions
+       advent-of-code-2019 debug <program>  debug an IntCode program read from a file
+       advent-of-code-2019 cfg <program>    print the control-flow graph of a program as DOT
+       advent-of-code-2019 ascii <program>  run a program that talks ASCII interactively
+       advent-of-code-2019 screen <program> [image.ppm|image.pbm]
+                                            run a program that draws tiles and show the picture";
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [] => solutions(),
+        ["debug", program] => debug(program),
+        ["cfg", program] => cfg(program),
+        ["ascii", program] => ascii(program),
+        ["screen", program] => screen(program, None),
+        ["screen", program, image] => screen(program, Some(image)),
+        _ => eprintln!("{}", USAGE),
+    }
+}
+
+fn solutions() {
+    aoc_main! { lib = advent_of_code_2019 }
+
+    main()
+}
+
+fn debug(path: &str) {
+    let vm: IntCodeVM = match IntCodeVM::from_file(path) {
+        Ok(vm) => vm,
+        Err(e) => return eprintln!("{}: {}", path, e),
+    };
+    let stdin = std::io::stdin();
+
+    Debugger::new(vm)
+        .repl(stdin.lock(), std::io::stdout())
+        .expect("failed to run the debugger");
+}
+
+fn cfg(path: &str) {
+    let vm: IntCodeVM = match IntCodeVM::from_file(path) {
+        Ok(vm) => vm,
+        Err(e) => return eprintln!("{}: {}", path, e),
+    };
+
+    let image = vm
+        .memory()
+        .as_slice()
+        .expect("parsed programs use dense memory");
+
+    ControlFlowGraph::new(image)
+        .write_dot(std::io::stdout().lock())
+        .expect("failed to write the graph");
+}
+
+fn ascii(path: &str) {
+    let vm: IntCodeVM = match IntCodeVM::from_file(path) {
+        Ok(vm) => vm,
+        Err(e) => return eprintln!("{}: {}", path, e),
+    };
+    let stdin = std::io::stdin();
+
+    AsciiVM::new(vm)
+        .interact(stdin.lock(), std::io::stdout())
+        .expect("failed to run the program");
+}
+
+fn screen(path: &str, image: Option<&str>) {
+    let mut vm: IntCodeVM = match IntCodeVM::from_file(path) {
+        Ok(vm) => vm,
+        Err(e) => return eprintln!("{}: {}", path, e),
+    };
+    let mut screen = Screen::arcade();
+    let result = vm.run(&mut (), &mut screen);
+
+    screen
+        .render(std::io::stdout().lock())
+        .expect("failed to draw the screen");
+
+    if let Err(e) = result {
+        eprintln!("{}", e);
+    }
+
+    let image = match image {
+        Some(image) => image,
+        None => return,
+    };
+    let written = std::fs::File::create(image).and_then(|file| {
+        let out = std::io::BufWriter::new(file);
+
+        if image.ends_with(".pbm") {
+            screen.write_pbm(out)
+        } else {
+            screen.write_ppm(out)
+        }
+    });
+
+    if let Err(e) = written {
+        eprintln!("{}: {}", image, e);
+    }
+}